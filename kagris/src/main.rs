@@ -6,33 +6,60 @@ use bevy::{
 };
 use bevy_egui::EguiPlugin;
 use colored::Colorize;
-use kgrs_audio::music::*;
-use kgrs_config::Config;
+use kgrs_audio::{music::*, sfx::SfxPlugin};
+use kgrs_config::{buffer::ConfigBufferPlugin, path, validation::ConfigIssues, Config};
 use kgrs_const::color::BG_COL;
-use kgrs_core::{board::BoardPlugin, mino::MinoPlugin};
+use kgrs_core::{
+    board::BoardPlugin, effect::EffectPlugin, input::ActionPlugin, layout::LayoutPlugin,
+    mino::MinoPlugin, mode::ModePlugin, score::ScorePlugin, stats::StatsPlugin, theme::ThemePlugin,
+};
 use kgrs_debug::{debug_ui::DebugUiPlugin, toggle_fullscreen::ToggleFullscreenPlugin};
+use kgrs_ui::{hud::HudPlugin, menu::MenuPlugin};
+use kgrs_util::state::AppState;
 
 fn main() {
     // The application
     let mut app = App::new();
+    path::apply_args(std::env::args().skip(1));
+    let (config, issues) = Config::load_checked();
 
     // Plugins
-    app.add_plugins(default_plugins())
+    app.add_plugins(default_plugins(&config))
+        .add_plugin(ConfigBufferPlugin)
         .add_plugin(EguiPlugin)
         .add_plugin(FrameTimeDiagnosticsPlugin)
         .add_plugin(EntityCountDiagnosticsPlugin)
+        .add_plugin(ActionPlugin)
         .add_plugin(DebugUiPlugin)
         .add_plugin(ToggleFullscreenPlugin)
+        .add_plugin(ThemePlugin)
+        .add_plugin(LayoutPlugin)
         .add_plugin(BoardPlugin)
-        .add_plugin(MinoPlugin);
+        .add_plugin(MinoPlugin)
+        .add_plugin(EffectPlugin)
+        .add_plugin(ModePlugin)
+        .add_plugin(ScorePlugin)
+        .add_plugin(StatsPlugin)
+        .add_plugin(HudPlugin)
+        .add_plugin(MenuPlugin)
+        .add_plugin(MusicPlugin)
+        .add_plugin(SfxPlugin);
+
+    // States
+    app.add_state(if issues.is_empty() {
+        AppState::ProfileSelect
+    } else {
+        AppState::ConfigPrompt
+    });
 
     // Startup systems
     app.add_startup_system_to_stage(StartupStage::PreStartup, pre_startup)
-        .add_startup_system(setup_camera)
-        .add_startup_system(setup_music);
+        .add_startup_system(setup_camera);
 
     // Resources
-    app.insert_resource(ClearColor(BG_COL));
+    app.insert_resource(ClearColor(BG_COL))
+        .insert_resource(config)
+        .insert_resource(ConfigIssues(issues));
 
     // In development
     #[cfg(debug_assertions)]
@@ -68,8 +95,7 @@ fn setup_camera(mut cmds: Commands) {
     cmds.spawn(Camera2dBundle::default());
 }
 
-fn default_plugins() -> PluginGroupBuilder {
-    let config = Config::load();
+fn default_plugins(config: &Config) -> PluginGroupBuilder {
     DefaultPlugins.set(WindowPlugin {
         window: WindowDescriptor {
             title: format!("KaGRiS v{}", env!("CARGO_PKG_VERSION")),
@@ -1,3 +1,4 @@
 //! Audio logics for KaGRoS
 
 pub mod music;
+pub mod sfx;
@@ -1,16 +1,546 @@
-use bevy::prelude::*;
-use kgrs_config::Config;
-
-/// Play music
-pub fn setup_music(asset_server: Res<AssetServer>, audio: Res<Audio>) {
-    let volume = Config::load().music_volume;
-    if 0. < volume {
-        audio.play_with_settings(
-            asset_server.load("musics/Fugue-in-Glop-de-Bal.ogg"), // TODO: more musics
-            PlaybackSettings::LOOP.with_volume(volume / 50.),
-        );
-        info!("Playing music");
+//! Music played in the menus and in game
+//!
+//! The tracks are listed in `assets/musics/playlist.json`.
+//! The menus and each game mode can have their own tracks,
+//! and the music crossfades when the screen changes between them.
+//! The length of each track is measured by decoding it in the background once it is loaded.
+//!
+//! While the stack is higher than the danger height, the music gets intense
+//! by switching to the intense stem of the track or by raising the tempo.
+
+use bevy::{
+    asset::FileAssetIo,
+    audio::{AudioSink, Source},
+    prelude::*,
+    tasks::{AsyncComputeTaskPool, Task},
+    utils::HashMap,
+};
+use futures_lite::future;
+use kgrs_config::{Config, Repeat};
+use kgrs_core::{event::StackHeightEvent, mode::GameMode};
+use kgrs_util::state::AppState;
+use rand::seq::SliceRandom;
+use serde::Deserialize;
+use serde_json::from_slice;
+use std::{fs, io, path::Path};
+
+/// Directory of the musics in the assets directory
+const MUSICS_DIR: &str = "musics";
+
+/// File listing the tracks in the musics directory
+const PLAYLIST: &str = "playlist.json";
+
+/// Duration of the crossfade between the scenes (Second)
+const CROSSFADE: f32 = 1.5;
+
+/// Duration of the transition to and from the intense music (Second)
+const INTENSITY_FADE: f32 = 1.;
+
+/// Playback speed of the tracks without the intense stem in danger
+const DANGER_SPEED: f32 = 1.12;
+
+pub struct MusicPlugin;
+
+impl Plugin for MusicPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MusicPlayer>()
+            .add_startup_system(load_playlist)
+            .add_system(apply_music_volume)
+            .add_system(select_music.after(apply_music_volume))
+            .add_system(detect_danger)
+            .add_system(measure_tracks)
+            .add_system(update_music.after(select_music).after(detect_danger));
+    }
+}
+
+/// Tracks listed in the playlist file
+#[derive(Deserialize, Default)]
+pub struct Playlist {
+    /// Tracks by their names
+    tracks: HashMap<String, Track>,
+    /// Names of the tracks played in the menus
+    menu: Vec<String>,
+    /// Names of the tracks played in game
+    game: Vec<String>,
+    /// Names of the tracks played in game of each mode instead of `game`
+    #[serde(default)]
+    modes: HashMap<GameMode, Vec<String>>,
+}
+
+impl Playlist {
+    /// Returns the names of the tracks played in the scene.
+    fn tracks_of(&self, scene: Scene) -> &[String] {
+        match scene {
+            Scene::Menu => &self.menu,
+            Scene::Game(mode) => self.modes.get(&mode).unwrap_or(&self.game),
+        }
+    }
+}
+
+/// Track in the playlist
+#[derive(Deserialize, Clone)]
+struct Track {
+    /// File in the musics directory
+    file: String,
+    /// File of the intense stem played in sync with the track in danger
+    #[serde(default)]
+    intense: Option<String>,
+}
+
+/// Scene which the music is selected for
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Scene {
+    Menu,
+    Game(GameMode),
+}
+
+impl Scene {
+    fn of(state: &AppState, mode: GameMode) -> Self {
+        match state {
+            AppState::InGame | AppState::Paused => Scene::Game(mode),
+            _ => Scene::Menu,
+        }
+    }
+}
+
+/// Music manager playing the tracks of the current scene
+#[derive(Resource, Default)]
+pub struct MusicPlayer {
+    playlist: Playlist,
+    /// Sounds of the tracks and the stems by the file names.
+    /// Loaded up front so that a track and its stem start in sync.
+    sources: HashMap<String, Handle<AudioSource>>,
+    /// Lengths of the loaded tracks by the file names (Second)
+    lengths: HashMap<String, f32>,
+    /// Tracks being decoded to measure their lengths by the file names
+    measuring: HashMap<String, Task<f32>>,
+    /// Scene of the playing tracks
+    scene: Option<Scene>,
+    /// Names of the tracks of the scene in the playing order
+    order: Vec<String>,
+    /// Index of the playing track in `order`
+    position: usize,
+    /// Volume percentage (0-100)
+    volume: f32,
+    /// Track being played. `None` after the last track without repeat.
+    playing: Option<Playing>,
+    /// Tracks of the previous scene fading out
+    fading: Vec<Playing>,
+    /// Whether the stack is higher than the danger height
+    is_in_danger: bool,
+    /// How intense the music is (0-1)
+    intensity: f32,
+}
+
+/// Track being played
+struct Playing {
+    sink: Handle<AudioSink>,
+    /// Sink of the intense stem
+    stem: Option<Handle<AudioSink>>,
+    /// File of the track
+    file: String,
+    /// Played time (Second)
+    elapsed: f32,
+    /// Multiplier of the volume for the crossfade (0-1)
+    fade: f32,
+}
+
+impl MusicPlayer {
+    /// Changes the volume percentage (0-100) of the playing music.
+    pub fn set_volume(&mut self, volume: f32, audio_sinks: &Assets<AudioSink>) {
+        self.volume = volume;
+        self.apply_volume(audio_sinks);
+    }
+
+    /// Sets the volume and the speed of the sinks by the fades and the intensity.
+    fn apply_volume(&self, audio_sinks: &Assets<AudioSink>) {
+        for playing in self.playing.iter().chain(&self.fading) {
+            let volume = sink_volume(self.volume) * playing.fade;
+            let (mix, speed) = intensity_mix(self.intensity, playing.stem.is_some());
+            if let Some(sink) = audio_sinks.get(&playing.sink) {
+                sink.set_volume(volume * (1. - mix));
+                sink.set_speed(speed);
+            }
+            if let Some(stem) = playing.stem.as_ref().and_then(|s| audio_sinks.get(s)) {
+                stem.set_volume(volume * mix);
+            }
+        }
+    }
+
+    /// Switches to the tracks of the scene.
+    /// The playing track fades out while the new one fades in.
+    fn change_scene(&mut self, scene: Scene, shuffle: bool, ctx: &PlayContext) {
+        let tracks = self.playlist.tracks_of(scene).to_vec();
+        let previous = self.scene.map(|s| self.playlist.tracks_of(s).to_vec());
+        self.scene = Some(scene);
+        // Keep playing if the scenes share the same tracks.
+        if previous.as_ref() == Some(&tracks) && self.playing.is_some() {
+            return;
+        }
+
+        let fade = match self.playing.take() {
+            Some(playing) => {
+                self.fading.push(playing);
+                0.
+            }
+            None => 1.,
+        };
+        self.order = play_order(&tracks, shuffle);
+        self.position = 0;
+        self.start(fade, ctx);
+    }
+
+    /// Plays the track at `position` from the start.
+    fn start(&mut self, fade: f32, ctx: &PlayContext) {
+        let track = match self
+            .order
+            .get(self.position)
+            .and_then(|name| self.playlist.tracks.get(name))
+        {
+            Some(track) => track,
+            None => return,
+        };
+        // Play even if muted so that the volume can be changed later.
+        // The volumes are set by `apply_volume` every frame.
+        let play = |file: &String| {
+            let source = self
+                .sources
+                .get(file)
+                .cloned()
+                .unwrap_or_else(|| ctx.asset_server.load(Path::new(MUSICS_DIR).join(file)));
+            let weak_handle = ctx
+                .audio
+                .play_with_settings(source, PlaybackSettings::ONCE.with_volume(0.));
+            ctx.audio_sinks.get_handle(weak_handle)
+        };
+        info!("Playing {}", self.order[self.position]);
+        self.playing = Some(Playing {
+            sink: play(&track.file),
+            stem: track.intense.as_ref().map(play),
+            file: track.file.clone(),
+            elapsed: 0.,
+            fade,
+        });
+    }
+
+    /// Plays the next track by the repeat mode.
+    fn next(&mut self, config: &Config, ctx: &PlayContext) {
+        match next_position(self.position, self.order.len(), config.music_repeat) {
+            Some(position) => {
+                if position == 0 && config.music_shuffle && config.music_repeat == Repeat::All {
+                    self.order = play_order(&self.order, true);
+                }
+                self.position = position;
+                self.start(1., ctx);
+            }
+            None => {
+                info!("Finished the playlist");
+                self.playing = None;
+            }
+        }
+    }
+}
+
+/// Resources to start playing a track
+struct PlayContext<'a> {
+    audio: &'a Audio,
+    asset_server: &'a AssetServer,
+    audio_sinks: &'a Assets<AudioSink>,
+}
+
+/// Loads the playlist and the tracks from the assets directory.
+fn load_playlist(asset_server: Res<AssetServer>, mut player: ResMut<MusicPlayer>) {
+    let file = FileAssetIo::get_base_path()
+        .join("assets")
+        .join(MUSICS_DIR)
+        .join(PLAYLIST);
+    match read_playlist(&file) {
+        Ok(playlist) => {
+            info!("Loaded {} tracks", playlist.tracks.len());
+            player.sources = playlist
+                .tracks
+                .values()
+                .flat_map(|track| [Some(&track.file), track.intense.as_ref()])
+                .flatten()
+                .map(|file| {
+                    let source = asset_server.load(Path::new(MUSICS_DIR).join(file));
+                    (file.clone(), source)
+                })
+                .collect();
+            player.playlist = playlist;
+        }
+        Err(why) => warn!("Failed to read {}: {}", file.display(), why),
+    }
+}
+
+/// Reads the playlist file and drops the names of the unknown tracks.
+fn read_playlist(file: &Path) -> io::Result<Playlist> {
+    let mut playlist: Playlist = from_slice(&fs::read(file)?)?;
+    let tracks = playlist.tracks.clone();
+    let lists = playlist
+        .modes
+        .values_mut()
+        .chain([&mut playlist.menu, &mut playlist.game]);
+    for list in lists {
+        list.retain(|name| {
+            let is_known = tracks.contains_key(name);
+            if !is_known {
+                warn!("Unknown track in {}: {}", PLAYLIST, name);
+            }
+            is_known
+        });
+    }
+    Ok(playlist)
+}
+
+/// Applies the music volume when the config is changed.
+fn apply_music_volume(
+    config: Res<Config>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut player: ResMut<MusicPlayer>,
+) {
+    if config.is_changed() && player.volume != config.music_volume {
+        player.set_volume(config.music_volume, &audio_sinks);
+    }
+}
+
+/// Switches the music when the scene is changed.
+fn select_music(
+    state: Res<State<AppState>>,
+    mode: Res<GameMode>,
+    config: Res<Config>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut player: ResMut<MusicPlayer>,
+) {
+    let scene = Scene::of(state.current(), *mode);
+    if player.scene != Some(scene) {
+        let ctx = PlayContext {
+            audio: &audio,
+            asset_server: &asset_server,
+            audio_sinks: &audio_sinks,
+        };
+        player.change_scene(scene, config.music_shuffle, &ctx);
+    }
+}
+
+/// Watches the stack height to make the music intense in danger.
+fn detect_danger(
+    mut height_reader: EventReader<StackHeightEvent>,
+    config: Res<Config>,
+    mut player: ResMut<MusicPlayer>,
+) {
+    if let Some(event) = height_reader.iter().last() {
+        let is_in_danger = is_in_danger(event.height, config.danger_height());
+        if player.is_in_danger != is_in_danger {
+            info!(
+                "Stack height {}: {} danger",
+                event.height,
+                if is_in_danger { "in" } else { "out of" }
+            );
+            player.is_in_danger = is_in_danger;
+        }
+    }
+}
+
+/// Measures the lengths of the loaded tracks in the background.
+fn measure_tracks(sources: Res<Assets<AudioSource>>, mut player: ResMut<MusicPlayer>) {
+    let player = &mut *player;
+    for (file, handle) in &player.sources {
+        if player.lengths.contains_key(file) || player.measuring.contains_key(file) {
+            continue;
+        }
+        if let Some(source) = sources.get(handle) {
+            let source = source.clone();
+            let task = AsyncComputeTaskPool::get().spawn(async move { source_length(&source) });
+            player.measuring.insert(file.clone(), task);
+        }
+    }
+    player.measuring.retain(
+        |file, task| match future::block_on(future::poll_once(task)) {
+            Some(length) => {
+                player.lengths.insert(file.clone(), length);
+                false
+            }
+            None => true,
+        },
+    );
+}
+
+/// Returns the length of the sound (Second).
+///
+/// The sound is decoded to the end if the format doesn't tell its length.
+fn source_length(source: &AudioSource) -> f32 {
+    let decoder = source.decoder();
+    if let Some(duration) = decoder.total_duration() {
+        return duration.as_secs_f32();
+    }
+    let samples_per_sec = decoder.sample_rate() as f32 * decoder.channels() as f32;
+    decoder.count() as f32 / samples_per_sec
+}
+
+/// Advances the crossfades and plays the next track at the end of a track.
+fn update_music(
+    time: Res<Time>,
+    config: Res<Config>,
+    audio: Res<Audio>,
+    asset_server: Res<AssetServer>,
+    audio_sinks: Res<Assets<AudioSink>>,
+    mut player: ResMut<MusicPlayer>,
+) {
+    let delta = time.delta_seconds();
+    let fade_step = delta / CROSSFADE;
+
+    // Calm down out of game even if the last game ended in danger.
+    let is_intense = config.adaptive_music
+        && player.is_in_danger
+        && matches!(player.scene, Some(Scene::Game(_)));
+    let intensity_step = delta / INTENSITY_FADE;
+    player.intensity = if is_intense {
+        (player.intensity + intensity_step).min(1.)
+    } else {
+        (player.intensity - intensity_step).max(0.)
+    };
+    let intensity = player.intensity;
+
+    player.fading.retain_mut(|playing| {
+        playing.fade -= fade_step;
+        let is_faded_out = playing.fade <= 0.;
+        if is_faded_out {
+            // Dropping the sinks would leave the sounds playing.
+            for sink in [Some(&playing.sink), playing.stem.as_ref()]
+                .into_iter()
+                .flatten()
+                .filter_map(|sink| audio_sinks.get(sink))
+            {
+                sink.stop();
+            }
+        }
+        !is_faded_out
+    });
+
+    let player = &mut *player;
+    let lengths = &player.lengths;
+    let is_finished = match &mut player.playing {
+        // The sink is available once the track is loaded and started.
+        Some(playing) if audio_sinks.get(&playing.sink).is_some() => {
+            let (_, speed) = intensity_mix(intensity, playing.stem.is_some());
+            playing.elapsed += delta * speed;
+            playing.fade = (playing.fade + fade_step).min(1.);
+            // Wait for the length until the track is measured.
+            lengths
+                .get(&playing.file)
+                .is_some_and(|length| *length <= playing.elapsed)
+        }
+        _ => false,
+    };
+    if is_finished {
+        let ctx = PlayContext {
+            audio: &audio,
+            asset_server: &asset_server,
+            audio_sinks: &audio_sinks,
+        };
+        player.next(&config, &ctx);
+    }
+
+    player.apply_volume(&audio_sinks);
+}
+
+/// Returns the tracks in the playing order.
+fn play_order(tracks: &[String], shuffle: bool) -> Vec<String> {
+    let mut order = tracks.to_vec();
+    if shuffle {
+        order.shuffle(&mut rand::thread_rng());
+    }
+    order
+}
+
+/// Returns the position of the track played after the track at `position`,
+/// or `None` to stop playing.
+fn next_position(position: usize, len: usize, repeat: Repeat) -> Option<usize> {
+    if len == 0 {
+        return None;
+    }
+    match repeat {
+        Repeat::Off => (position + 1 < len).then_some(position + 1),
+        Repeat::All => Some((position + 1) % len),
+        Repeat::One => Some(position),
+    }
+}
+
+/// Whether the stack is higher than the danger height.
+fn is_in_danger(height: usize, danger_height: usize) -> bool {
+    danger_height < height
+}
+
+/// Returns the volume ratio of the intense stem and the playback speed by the intensity.
+///
+/// The tracks without the stem get faster instead.
+fn intensity_mix(intensity: f32, has_stem: bool) -> (f32, f32) {
+    if has_stem {
+        (intensity, 1.)
     } else {
-        info!("Music is muted");
+        (0., 1. + (DANGER_SPEED - 1.) * intensity)
+    }
+}
+
+/// Converts the volume percentage to the volume of `AudioSink`.
+pub(crate) fn sink_volume(volume: f32) -> f32 {
+    volume / 50.
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn next_track_by_repeat_mode() {
+        assert_eq!(next_position(0, 3, Repeat::Off), Some(1));
+        assert_eq!(next_position(2, 3, Repeat::Off), None);
+        assert_eq!(next_position(2, 3, Repeat::All), Some(0));
+        assert_eq!(next_position(1, 3, Repeat::One), Some(1));
+        assert_eq!(next_position(0, 0, Repeat::All), None);
+    }
+
+    #[test]
+    fn intense_stem_or_tempo_in_danger() {
+        assert!(!is_in_danger(14, 14));
+        assert!(is_in_danger(15, 14));
+        assert_eq!(intensity_mix(0., true), (0., 1.));
+        assert_eq!(intensity_mix(1., true), (1., 1.));
+        assert_eq!(intensity_mix(0., false), (0., 1.));
+        assert_eq!(intensity_mix(1., false), (0., DANGER_SPEED));
+    }
+
+    #[test]
+    fn mode_tracks_override_game_tracks() {
+        let playlist: Playlist = serde_json::from_str(
+            r#"{
+                "tracks": {
+                    "A": { "file": "a.ogg" },
+                    "B": { "file": "b.ogg" }
+                },
+                "menu": ["A"],
+                "game": ["A", "B"],
+                "modes": { "PcPractice": ["B"] }
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(playlist.tracks_of(Scene::Menu), ["A"]);
+        assert_eq!(playlist.tracks_of(Scene::Game(GameMode::Free)), ["A", "B"]);
+        assert_eq!(playlist.tracks_of(Scene::Game(GameMode::PcPractice)), ["B"]);
+    }
+
+    #[test]
+    fn length_of_decoded_sound() {
+        let file = concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/../kagris/assets/sfx/default/top_out.wav"
+        );
+        let source = AudioSource {
+            bytes: fs::read(file).unwrap().into(),
+        };
+        assert!((source_length(&source) - 0.8).abs() < 0.01);
     }
 }
@@ -0,0 +1,352 @@
+//! Sound effects played on the game events
+//!
+//! The sound of each effect is looked up in `sfx.json` of the selected pack
+//! in `assets/sfx/<pack>/`, so a pack can be swapped without rebuilding.
+//! The effects missing from the pack are not played,
+//! and the unknown effects in the manifest are ignored.
+//! The sounds can be Ogg Vorbis or WAV files.
+//! The pack name and the sound files must be plain file names,
+//! so a pack never reaches out of its directory.
+
+use crate::music::sink_volume;
+use bevy::{asset::FileAssetIo, prelude::*, utils::HashMap};
+use kgrs_config::Config;
+use kgrs_core::{
+    event::{GameOverEvent, LockEvent, MoveEvent},
+    score::{update_score, Score},
+};
+use kgrs_util::function::path::is_file_name;
+use serde::Deserialize;
+use serde_json::{from_slice, from_value, Value};
+use std::{fs, io, path::Path};
+
+/// Directory of the packs in the assets directory
+const PACKS_DIR: &str = "sfx";
+
+/// File mapping the effects to the sound files in a pack
+const MANIFEST: &str = "sfx.json";
+
+/// Combo count at which the pitch of the combo sound stops rising
+const MAX_COMBO_PITCH: u32 = 12;
+
+pub struct SfxPlugin;
+
+impl Plugin for SfxPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SfxPack>()
+            .insert_resource(SfxPackList(SfxPack::list()))
+            .add_system(load_sfx_pack)
+            .add_system(play_move_sfx)
+            // Read the score before the lock is added to it.
+            .add_system(play_lock_sfx.before(update_score))
+            .add_system(play_game_over_sfx);
+    }
+}
+
+/// Sound effect
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum Sfx {
+    Move,
+    HardDrop,
+    /// Lock without a hard drop.
+    Lock,
+    Single,
+    Double,
+    Triple,
+    Quad,
+    /// Played at a higher pitch as the combo goes on.
+    Combo,
+    B2b,
+    PerfectClear,
+    TopOut,
+}
+
+/// Sounds of the loaded pack
+#[derive(Resource, Default)]
+pub struct SfxPack {
+    /// Name of the pack
+    name: String,
+    sounds: HashMap<Sfx, Handle<AudioSource>>,
+}
+
+/// Names of the available SFX packs
+#[derive(Resource)]
+pub struct SfxPackList(pub Vec<String>);
+
+impl SfxPack {
+    /// Returns the names of the packs with a manifest in the packs directory.
+    pub fn list() -> Vec<String> {
+        let dir = FileAssetIo::get_base_path().join("assets").join(PACKS_DIR);
+        let mut names: Vec<_> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    if !path.join(MANIFEST).is_file() {
+                        return None;
+                    }
+                    Some(path.file_name()?.to_str()?.to_string())
+                })
+                .filter(|name| is_file_name(name))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names
+    }
+
+    /// Plays the effect at the volume percentage (0-100).
+    /// `pitch` is the playback speed and `1.` plays the sound as it is.
+    pub fn play(&self, audio: &Audio, sfx: Sfx, pitch: f32, volume: f32) {
+        if volume <= 0. {
+            return;
+        }
+        if let Some(sound) = self.sounds.get(&sfx) {
+            audio.play_with_settings(
+                sound.clone(),
+                PlaybackSettings::ONCE
+                    .with_volume(sink_volume(volume))
+                    .with_speed(pitch),
+            );
+        }
+    }
+}
+
+/// Loads the pack selected in the config when it is changed.
+fn load_sfx_pack(config: Res<Config>, asset_server: Res<AssetServer>, mut pack: ResMut<SfxPack>) {
+    if !config.is_changed() || pack.name == config.sfx_pack {
+        return;
+    }
+    pack.name = config.sfx_pack.clone();
+    if !is_file_name(&pack.name) {
+        warn!("Invalid SFX pack name {:?}", pack.name);
+        pack.sounds.clear();
+        return;
+    }
+    let dir = Path::new(PACKS_DIR).join(&pack.name);
+    let pack_dir = FileAssetIo::get_base_path().join("assets").join(&dir);
+    let manifest = pack_dir.join(MANIFEST);
+    let files = match read_manifest(&manifest) {
+        Ok(files) => files,
+        Err(why) => {
+            warn!("Failed to read {}: {}", manifest.display(), why);
+            HashMap::default()
+        }
+    };
+    let (files, missing): (Vec<_>, Vec<_>) = files
+        .into_iter()
+        .partition(|(_, file)| pack_dir.join(file).is_file());
+    if !missing.is_empty() {
+        let missing: Vec<_> = missing.iter().map(|(_, file)| file.as_str()).collect();
+        warn!(
+            "Missing sounds in {}: {}",
+            pack_dir.display(),
+            missing.join(", ")
+        );
+    }
+    pack.sounds = files
+        .into_iter()
+        .map(|(sfx, file)| (sfx, asset_server.load(dir.join(file))))
+        .collect();
+    info!(
+        "Loaded SFX pack {} ({} sounds)",
+        pack.name,
+        pack.sounds.len()
+    );
+}
+
+/// Reads the sound files of the effects from the manifest of a pack.
+fn read_manifest(file: &Path) -> io::Result<HashMap<Sfx, String>> {
+    let files: HashMap<String, String> = from_slice(&fs::read(file)?)?;
+    Ok(files
+        .into_iter()
+        .filter_map(
+            |(name, sound)| match from_value(Value::String(name.clone())) {
+                Ok(_) if !is_file_name(&sound) => {
+                    warn!(
+                        "Ignoring invalid sound file {:?} in {}",
+                        sound,
+                        file.display()
+                    );
+                    None
+                }
+                Ok(sfx) => Some((sfx, sound)),
+                Err(_) => {
+                    warn!("Ignoring unknown effect `{}` in {}", name, file.display());
+                    None
+                }
+            },
+        )
+        .collect())
+}
+
+/// Plays the move sound when the mino is shifted.
+fn play_move_sfx(
+    mut move_reader: EventReader<MoveEvent>,
+    pack: Res<SfxPack>,
+    audio: Res<Audio>,
+    config: Res<Config>,
+) {
+    // Shifts of the same frame sound as one.
+    if move_reader.iter().last().is_some() {
+        pack.play(&audio, Sfx::Move, 1., config.sfx_volume);
+    }
+}
+
+/// Plays the sounds of the locked minoes.
+fn play_lock_sfx(
+    mut lock_reader: EventReader<LockEvent>,
+    score: Res<Score>,
+    pack: Res<SfxPack>,
+    audio: Res<Audio>,
+    config: Res<Config>,
+) {
+    let mut score = *score;
+    for lock in lock_reader.iter() {
+        for (sfx, pitch) in lock_sfx(&score, lock) {
+            pack.play(&audio, sfx, pitch, config.sfx_volume);
+        }
+        score = score.after_lock(lock);
+    }
+}
+
+/// Plays the top out sound.
+fn play_game_over_sfx(
+    mut game_over_reader: EventReader<GameOverEvent>,
+    pack: Res<SfxPack>,
+    audio: Res<Audio>,
+    config: Res<Config>,
+) {
+    if game_over_reader.iter().any(|game_over| game_over.top_out) {
+        pack.play(&audio, Sfx::TopOut, 1., config.sfx_volume);
+    }
+}
+
+/// Returns the effects of the lock with their pitches.
+///
+/// `score` is the score before the lock.
+fn lock_sfx(score: &Score, lock: &LockEvent) -> Vec<(Sfx, f32)> {
+    let after = score.after_lock(lock);
+    let mut sfx = Vec::new();
+    sfx.push(if 0 < lock.drop_distance {
+        (Sfx::HardDrop, 1.)
+    } else {
+        (Sfx::Lock, 1.)
+    });
+    let clear = match lock.lines {
+        0 => None,
+        1 => Some(Sfx::Single),
+        2 => Some(Sfx::Double),
+        3 => Some(Sfx::Triple),
+        _ => Some(Sfx::Quad),
+    };
+    if let Some(clear) = clear {
+        sfx.push((clear, 1.));
+    }
+    if let Some(combo) = after.combo.filter(|combo| 0 < *combo) {
+        sfx.push((Sfx::Combo, combo_pitch(combo)));
+    }
+    if 0 < lock.lines && score.b2b && after.b2b {
+        sfx.push((Sfx::B2b, 1.));
+    }
+    if lock.perfect_clear {
+        sfx.push((Sfx::PerfectClear, 1.));
+    }
+    sfx
+}
+
+/// Raises the pitch a semitone per combo up to an octave.
+fn combo_pitch(combo: u32) -> f32 {
+    2f32.powf(combo.min(MAX_COMBO_PITCH) as f32 / 12.)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use kgrs_core::score::Spin;
+
+    fn lock(lines: usize) -> LockEvent {
+        LockEvent {
+            drop_distance: 5,
+            soft_drop_distance: 0,
+            spin: Spin::No,
+            lines,
+            stack_height: 0,
+            perfect_clear: false,
+        }
+    }
+
+    fn names(sfx: &[(Sfx, f32)]) -> Vec<Sfx> {
+        sfx.iter().map(|(sfx, _)| *sfx).collect()
+    }
+
+    #[test]
+    fn line_clears_by_size() {
+        let score = Score::default();
+        assert_eq!(names(&lock_sfx(&score, &lock(0))), [Sfx::HardDrop]);
+        assert_eq!(
+            names(&lock_sfx(&score, &lock(3))),
+            [Sfx::HardDrop, Sfx::Triple]
+        );
+    }
+
+    #[test]
+    fn combo_rises_in_pitch() {
+        let mut score = Score::default();
+        let mut pitches = Vec::new();
+        for _ in 0..3 {
+            let sfx = lock_sfx(&score, &lock(1));
+            pitches.extend(
+                sfx.iter()
+                    .filter(|(sfx, _)| *sfx == Sfx::Combo)
+                    .map(|(_, p)| *p),
+            );
+            score = score.after_lock(&lock(1));
+        }
+        // The first clear is not a combo.
+        assert_eq!(pitches.len(), 2);
+        assert!(1. < pitches[0] && pitches[0] < pitches[1]);
+        assert_eq!(combo_pitch(MAX_COMBO_PITCH + 5), 2.);
+    }
+
+    #[test]
+    fn back_to_back_and_perfect_clear() {
+        let score = Score::default().after_lock(&lock(4));
+        let mut pc = lock(4);
+        pc.perfect_clear = true;
+        let sfx = names(&lock_sfx(&score, &pc));
+        assert!(sfx.contains(&Sfx::B2b));
+        assert!(sfx.contains(&Sfx::PerfectClear));
+
+        // A single breaks the B2B without the sound.
+        assert!(!names(&lock_sfx(&score, &lock(1))).contains(&Sfx::B2b));
+    }
+
+    #[test]
+    fn manifest_ignores_unknown_effects_and_paths() {
+        let file = std::env::temp_dir().join(format!("kagris-sfx-{}.json", std::process::id()));
+        fs::write(
+            &file,
+            r#"{
+                "Move": "move.wav",
+                "Rotate": "rotate.wav",
+                "Lock": "../../../config.json",
+                "TopOut": "/etc/passwd"
+            }"#,
+        )
+        .unwrap();
+        let files = read_manifest(&file).unwrap();
+        fs::remove_file(&file).unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[&Sfx::Move], "move.wav");
+    }
+
+    #[test]
+    fn default_pack_has_every_sound() {
+        let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../kagris/assets/sfx/default");
+        let files = read_manifest(&dir.join(MANIFEST)).unwrap();
+        assert!(!files.is_empty());
+        for file in files.values() {
+            assert!(dir.join(file).is_file(), "{} is missing", file);
+        }
+    }
+}
@@ -0,0 +1,232 @@
+//! Key and gamepad button bindings of the actions
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, fmt};
+
+/// Action of the player bound to keys and gamepad buttons
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum Action {
+    MoveLeft,
+    MoveRight,
+    SoftDrop,
+    HardDrop,
+    RotateCw,
+    RotateCcw,
+    Rotate180,
+    Hold,
+    Restart,
+    Pause,
+    ToggleDebug,
+    ToggleFullscreen,
+}
+
+impl Action {
+    pub const ALL: [Self; 12] = [
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::SoftDrop,
+        Self::HardDrop,
+        Self::RotateCw,
+        Self::RotateCcw,
+        Self::Rotate180,
+        Self::Hold,
+        Self::Restart,
+        Self::Pause,
+        Self::ToggleDebug,
+        Self::ToggleFullscreen,
+    ];
+}
+
+impl fmt::Display for Action {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            Action::MoveLeft => "Move Left",
+            Action::MoveRight => "Move Right",
+            Action::SoftDrop => "Soft Drop",
+            Action::HardDrop => "Hard Drop",
+            Action::RotateCw => "Rotate CW",
+            Action::RotateCcw => "Rotate CCW",
+            Action::Rotate180 => "Rotate 180",
+            Action::Hold => "Hold",
+            Action::Restart => "Restart",
+            Action::Pause => "Pause",
+            Action::ToggleDebug => "Debug UI",
+            Action::ToggleFullscreen => "Fullscreen",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Inputs bound to each action.
+/// An action can have multiple inputs.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(transparent)]
+pub struct Bindings<T>(BTreeMap<Action, Vec<T>>);
+
+/// Keys bound to each action
+pub type KeyBindings = Bindings<KeyCode>;
+
+/// Gamepad buttons bound to each action
+pub type ButtonBindings = Bindings<GamepadButtonType>;
+
+impl Default for KeyBindings {
+    fn default() -> Self {
+        use KeyCode::*;
+        Self(BTreeMap::from([
+            (Action::MoveLeft, vec![Left]),
+            (Action::MoveRight, vec![Right]),
+            (Action::SoftDrop, vec![Down]),
+            (Action::HardDrop, vec![Space]),
+            (Action::RotateCw, vec![Up, X]),
+            (Action::RotateCcw, vec![Z, LControl]),
+            (Action::Rotate180, vec![A]),
+            (Action::Hold, vec![C, LShift]),
+            (Action::Restart, vec![R]),
+            (Action::Pause, vec![Escape]),
+            (Action::ToggleDebug, vec![F3]),
+            (Action::ToggleFullscreen, vec![F11]),
+        ]))
+    }
+}
+
+impl Default for ButtonBindings {
+    fn default() -> Self {
+        use GamepadButtonType::*;
+        Self(BTreeMap::from([
+            (Action::MoveLeft, vec![DPadLeft]),
+            (Action::MoveRight, vec![DPadRight]),
+            (Action::SoftDrop, vec![DPadDown]),
+            (Action::HardDrop, vec![DPadUp]),
+            (Action::RotateCw, vec![East]),
+            (Action::RotateCcw, vec![South]),
+            (Action::Rotate180, vec![North]),
+            (Action::Hold, vec![LeftTrigger, RightTrigger]),
+            (Action::Restart, vec![Select]),
+            (Action::Pause, vec![Start]),
+        ]))
+    }
+}
+
+impl<T: Copy + PartialEq> Bindings<T> {
+    /// Returns the inputs bound to the action.
+    pub fn inputs(&self, action: Action) -> &[T] {
+        self.0.get(&action).map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the action the input is bound to.
+    pub fn action_of(&self, input: T) -> Option<Action> {
+        self.0
+            .iter()
+            .find(|(_, inputs)| inputs.contains(&input))
+            .map(|(action, _)| *action)
+    }
+
+    /// Binds the input to the action.
+    ///
+    /// Returns `Err` with the other action if the input is already bound to it.
+    pub fn bind(&mut self, action: Action, input: T) -> Result<(), Action> {
+        match self.action_of(input) {
+            Some(other) if other != action => Err(other),
+            Some(_) => Ok(()),
+            None => {
+                self.0.entry(action).or_default().push(input);
+                Ok(())
+            }
+        }
+    }
+
+    /// Removes all the inputs bound to the action.
+    pub fn clear(&mut self, action: Action) {
+        self.0.remove(&action);
+    }
+
+    /// Returns the inputs bound to multiple actions and the actions.
+    pub fn conflicts(&self) -> Vec<(T, Vec<Action>)> {
+        let mut conflicts: Vec<(T, Vec<Action>)> = Vec::new();
+        for (action, inputs) in &self.0 {
+            for input in inputs {
+                match conflicts.iter_mut().find(|(i, _)| i == input) {
+                    Some((_, actions)) => actions.push(*action),
+                    None => conflicts.push((*input, vec![*action])),
+                }
+            }
+        }
+        conflicts.retain(|(_, actions)| 1 < actions.len());
+        conflicts
+    }
+}
+
+impl KeyBindings {
+    /// Returns the keys bound to the action.
+    pub fn keys(&self, action: Action) -> &[KeyCode] {
+        self.inputs(action)
+    }
+
+    /// Whether any key of the action is being pressed.
+    pub fn pressed(&self, action: Action, input: &Input<KeyCode>) -> bool {
+        input.any_pressed(self.keys(action).iter().copied())
+    }
+
+    /// Whether any key of the action was pressed in this frame.
+    pub fn just_pressed(&self, action: Action, input: &Input<KeyCode>) -> bool {
+        input.any_just_pressed(self.keys(action).iter().copied())
+    }
+
+    /// Whether any key of the action was pressed in this frame.
+    /// The keys are consumed so they don't affect the other systems.
+    pub fn clear_just_pressed(&self, action: Action, input: &mut Input<KeyCode>) -> bool {
+        // Don't short-circuit to consume all the keys.
+        self.keys(action).iter().fold(false, |pressed, key| {
+            input.clear_just_pressed(*key) | pressed
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn bind_detects_conflicts() {
+        let mut bindings = KeyBindings::default();
+        assert_eq!(
+            bindings.bind(Action::HardDrop, KeyCode::Up),
+            Err(Action::RotateCw)
+        );
+        assert_eq!(bindings.bind(Action::HardDrop, KeyCode::Space), Ok(()));
+        assert_eq!(bindings.bind(Action::HardDrop, KeyCode::W), Ok(()));
+        assert_eq!(
+            bindings.keys(Action::HardDrop),
+            [KeyCode::Space, KeyCode::W]
+        );
+
+        bindings.clear(Action::HardDrop);
+        assert!(bindings.keys(Action::HardDrop).is_empty());
+        assert_eq!(bindings.action_of(KeyCode::Space), None);
+    }
+
+    #[test]
+    fn conflicts_of_edited_bindings() {
+        let mut bindings = KeyBindings::default();
+        assert!(bindings.conflicts().is_empty());
+
+        bindings.0.insert(Action::Hold, vec![KeyCode::Space]);
+        assert_eq!(
+            bindings.conflicts(),
+            [(KeyCode::Space, vec![Action::HardDrop, Action::Hold])]
+        );
+    }
+
+    #[test]
+    fn any_bound_key_triggers_action() {
+        let bindings = KeyBindings::default();
+        let mut input = Input::default();
+        input.press(KeyCode::X);
+        assert!(bindings.just_pressed(Action::RotateCw, &input));
+        assert!(bindings.clear_just_pressed(Action::RotateCw, &mut input));
+        assert!(!bindings.just_pressed(Action::RotateCw, &input));
+        assert!(bindings.pressed(Action::RotateCw, &input));
+        assert!(!bindings.pressed(Action::RotateCcw, &input));
+    }
+}
@@ -0,0 +1,254 @@
+use crate::{migration::Loaded, path, profile::ActiveProfile, Config};
+use bevy::{app::AppExit, prelude::*, window::PresentMode};
+use serde_json::{to_value, Value};
+use std::{fs, time::SystemTime};
+
+/// Delay in seconds to write the config to the file after the last change
+const WRITE_BACK_DELAY: f32 = 1.;
+
+/// Interval in seconds to check whether config.json was edited
+const RELOAD_INTERVAL: f32 = 0.5;
+
+/// Holds `Config` and the active profile in memory as resources and writes them back to the files.
+/// config.json edited while the game is running is reloaded.
+///
+/// `Config` must be inserted as a resource before the app runs.
+pub struct ConfigBufferPlugin;
+
+impl Plugin for ConfigBufferPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigBuffer>()
+            .init_resource::<ActiveProfile>()
+            .add_system(apply_window_config)
+            .add_system(reload_config.before(write_back_config))
+            .add_system(update_profile.before(write_back_config))
+            .add_system(write_back_config)
+            .add_system_to_stage(CoreStage::Last, flush_config_on_exit);
+    }
+}
+
+/// State of writing back the config to the file
+#[derive(Resource)]
+pub struct ConfigBuffer {
+    /// Timer until the changed config is written.
+    /// `None` if there is no unsaved change.
+    write_timer: Option<Timer>,
+    /// Timer until the changed profile is written.
+    /// `None` if there is no unsaved change.
+    profile_timer: Option<Timer>,
+    /// Timer to check the modified time of config.json.
+    reload_timer: Timer,
+    /// Modified time of config.json when it was read or written last.
+    last_modified: Option<SystemTime>,
+    /// Whether the config was changed by reloading, so it doesn't need to be written.
+    is_reloaded: bool,
+}
+
+impl Default for ConfigBuffer {
+    fn default() -> Self {
+        Self {
+            write_timer: None,
+            profile_timer: None,
+            reload_timer: Timer::from_seconds(RELOAD_INTERVAL, TimerMode::Repeating),
+            last_modified: modified_time(),
+            is_reloaded: false,
+        }
+    }
+}
+
+/// Returns the modified time of config.json.
+fn modified_time() -> Option<SystemTime> {
+    fs::metadata(path::config_file())
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+/// Writes the config and the profile to the files
+/// when they haven't been changed for `WRITE_BACK_DELAY`.
+fn write_back_config(
+    time: Res<Time>,
+    config: Res<Config>,
+    profile: Res<ActiveProfile>,
+    mut buffer: ResMut<ConfigBuffer>,
+) {
+    if config.is_changed() && !config.is_added() && !buffer.is_reloaded {
+        buffer.write_timer = Some(Timer::from_seconds(WRITE_BACK_DELAY, TimerMode::Once));
+    }
+    buffer.is_reloaded = false;
+    if profile.is_changed() && !profile.is_added() {
+        buffer.profile_timer = Some(Timer::from_seconds(WRITE_BACK_DELAY, TimerMode::Once));
+    }
+
+    if let Some(timer) = &mut buffer.write_timer {
+        if timer.tick(time.delta()).just_finished() {
+            config.save();
+            buffer.write_timer = None;
+            buffer.last_modified = modified_time();
+        }
+    }
+    if let Some(timer) = &mut buffer.profile_timer {
+        if timer.tick(time.delta()).just_finished() {
+            if let Some(profile) = &profile.0 {
+                profile.save();
+            }
+            buffer.profile_timer = None;
+        }
+    }
+}
+
+/// Writes the unsaved config and profile before the app exits.
+fn flush_config_on_exit(
+    mut exit_reader: EventReader<AppExit>,
+    config: Res<Config>,
+    profile: Res<ActiveProfile>,
+    mut buffer: ResMut<ConfigBuffer>,
+) {
+    if exit_reader.iter().next().is_some() {
+        if buffer.write_timer.take().is_some() {
+            config.save();
+        }
+        if let (Some(_), Some(profile)) = (buffer.profile_timer.take(), &profile.0) {
+            profile.save();
+        }
+    }
+}
+
+/// Reloads config.json when it was edited outside the game.
+/// The file is kept as is, and is not reloaded while it has problems.
+fn reload_config(time: Res<Time>, mut config: ResMut<Config>, mut buffer: ResMut<ConfigBuffer>) {
+    if !buffer.reload_timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let modified = modified_time();
+    if modified.is_none() || modified == buffer.last_modified {
+        return;
+    }
+    buffer.last_modified = modified;
+
+    let mut issues = Vec::new();
+    let mut reloaded = match Config::read(&mut issues) {
+        Ok(Loaded::Current(config) | Loaded::Migrated { config, .. }) => config,
+        Err(why) => {
+            warn!("Failed to reload config.json: {}", why);
+            return;
+        }
+    };
+    issues.extend(reloaded.validate());
+    if !issues.is_empty() {
+        // Wait for the player to fix the file rather than resetting the fields.
+        let issues: Vec<_> = issues.iter().map(ToString::to_string).collect();
+        warn!("Skipped reloading config.json: {}", issues.join(", "));
+        return;
+    }
+    let changes = describe_changes(&config, &reloaded);
+    if changes.is_empty() {
+        return;
+    }
+    info!("Reloaded config.json: {}", changes.join(", "));
+    // The edited file takes priority over the unsaved changes.
+    buffer.write_timer = None;
+    buffer.is_reloaded = true;
+    *config = reloaded;
+}
+
+/// Fields only named when changed, as their values are too long to log
+const NAMED_ONLY: [&str; 2] = ["key_bindings", "button_bindings"];
+
+/// Describes the changed fields like `vsync: false -> true`.
+///
+/// The configs are compared in their JSON form, so every field is covered.
+fn describe_changes(old: &Config, new: &Config) -> Vec<String> {
+    let mut changes = Vec::new();
+    if let (Ok(old), Ok(new)) = (to_value(old), to_value(new)) {
+        diff_values("", &old, &new, &mut changes);
+    }
+    changes
+}
+
+/// Describes the changes between the values at `path`, walking into the objects.
+fn diff_values(path: &str, old: &Value, new: &Value, changes: &mut Vec<String>) {
+    if old == new {
+        return;
+    }
+    match (old, new) {
+        _ if NAMED_ONLY.contains(&path) => changes.push(path.to_string()),
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                let new_value = new.get(key).unwrap_or(&Value::Null);
+                diff_values(&path, old_value, new_value, changes);
+            }
+        }
+        _ => changes.push(format!("{}: {} -> {}", path, old, new)),
+    }
+}
+
+/// Copies the changed settings to the active profile.
+fn update_profile(config: Res<Config>, mut profile: ResMut<ActiveProfile>) {
+    if !config.is_changed() {
+        return;
+    }
+    // Don't touch the resource unless changed, to avoid writing the profile.
+    if matches!(&profile.0, Some(p) if !p.is_applied_to(&config)) {
+        if let Some(profile) = &mut profile.0 {
+            profile.update_from(&config);
+        }
+    }
+}
+
+/// Applies VSync and the window mode when the config is changed.
+fn apply_window_config(config: Res<Config>, mut windows: ResMut<Windows>) {
+    if !config.is_changed() {
+        return;
+    }
+    let window = match windows.get_primary_mut() {
+        Some(window) => window,
+        None => return,
+    };
+
+    let present_mode = if config.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    if window.present_mode() != present_mode {
+        info!("Setting the present mode to {:?}", present_mode);
+        window.set_present_mode(present_mode);
+    }
+
+    let window_mode = config.window_mode.to_window_mode();
+    if window.mode() != window_mode {
+        window.set_mode(window_mode);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::binding::Action;
+
+    #[test]
+    fn describe_changed_fields() {
+        let old = Config::default();
+        let mut new = old.clone();
+        assert!(describe_changes(&old, &new).is_empty());
+
+        new.vsync = true;
+        new.handling.arr = 0.;
+        new.theme = String::from("dark");
+        new.key_bindings.clear(Action::Hold);
+        assert_eq!(
+            describe_changes(&old, &new),
+            [
+                "handling.arr: 2.0 -> 0.0",
+                "key_bindings",
+                r#"theme: "default" -> "dark""#,
+                "vsync: false -> true",
+            ]
+        );
+    }
+}
@@ -1,18 +1,35 @@
 //! Config manager for KaGRiS
 
 use bevy::prelude::*;
+use binding::{ButtonBindings, KeyBindings};
+use migration::Loaded;
 use serde::{Deserialize, Serialize};
-use serde_json::{from_reader, to_writer};
+use serde_json::{from_reader, to_vec, Value};
 use std::{
-    fs::{File, OpenOptions},
+    fs::{self, File},
+    io::{self, ErrorKind},
     num::NonZeroU8,
+    ops::RangeInclusive,
+    process::Command,
 };
+use validation::ConfigIssue;
+
+pub mod binding;
+pub mod buffer;
+pub(crate) mod migration;
+pub mod path;
+pub mod profile;
+pub mod validation;
 
 /// Config manager for KaGRiS
 ///
+/// While the app is running, `Config` is held as a resource
+/// and written back to config.json by `buffer::ConfigBufferPlugin`.
+/// So systems should use `Res<Config>` instead of `Config::load`.
+///
 /// # Examples
 ///
-/// ```
+/// ```no_run
 /// use kgrs_config::Config;
 ///
 /// let mut config = Config::load();
@@ -25,7 +42,8 @@ use std::{
 /// // Save the config
 /// config.save();
 /// ```
-#[derive(Serialize, Deserialize)]
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
 pub struct Config {
     /// Version of the config
     version: ConfVer,
@@ -33,82 +51,238 @@ pub struct Config {
     pub vsync: bool,
     /// Opacity percentage of the grid (0-100)
     pub grid_opacity: u8,
+    /// Size percentage of the board, the HUD and the menus (50-150)
+    pub ui_scale: u8,
+    /// Name of the theme in `assets/themes`
+    pub theme: String,
+    /// Palette of the minoes replacing the theme colors
+    pub color_blind: ColorBlind,
+    /// Whether to draw a glyph of the kind on each block
+    pub mino_glyphs: bool,
+    /// Visual effects of the game
+    pub effects: Effects,
     /// Volume percentage of the music (0-100)
     pub music_volume: f32,
+    /// Whether to play the music in random order
+    pub music_shuffle: bool,
+    /// Repetition of the music
+    pub music_repeat: Repeat,
+    /// Whether the music gets intense while the stack is high
+    pub adaptive_music: bool,
+    /// Height percentage of the board above which the music gets intense (1-100)
+    pub danger_ratio: u8,
+    /// Volume percentage of the sound effects (0-100)
+    pub sfx_volume: f32,
+    /// Name of the sound effect pack in `assets/sfx`
+    pub sfx_pack: String,
     /// Window mode
     pub window_mode: WindowModeForConf,
+    /// Size of the board
+    pub board: BoardSize,
     /// Control handlings
     pub handling: Handling,
+    /// Keys bound to the actions
+    pub key_bindings: KeyBindings,
+    /// Gamepad buttons bound to the actions
+    pub button_bindings: ButtonBindings,
+    /// Percentage of the stick tilt to be read as a direction (0-90)
+    pub stick_deadzone: u8,
 }
 
 impl Config {
     /// Load from config.json
     pub fn load() -> Self {
-        match File::open("config.json") {
-            Ok(f) => {
-                match from_reader(f) {
-                    Ok(config) => config,
-                    Err(why) => {
-                        warn!("Failed to parse config.json: {}", why);
-                        default() // TODO: impl ask to initialize
-                    }
+        Self::load_checked().0
+    }
+
+    /// Load from config.json and returns the problems found in it.
+    ///
+    /// config.json is created with the default config on first run.
+    /// A config of an older version is migrated to the current version,
+    /// validated and saved after backing up the original file.
+    /// A config of an unknown version is read as the current one but never saved.
+    /// The invalid fields are reset to the default and the valid ones are kept.
+    pub fn load_checked() -> (Self, Vec<ConfigIssue>) {
+        path::create_dirs();
+        path::import_legacy_config();
+        let file = path::config_file();
+        let mut issues = Vec::new();
+        let mut config = match Self::read(&mut issues) {
+            Ok(Loaded::Current(config)) => config,
+            Ok(Loaded::Migrated { from, mut config }) => {
+                // Only the valid values are written over the original file.
+                issues.append(&mut config.validate());
+                if Self::backup(&from) {
+                    config.save();
                 }
+                return (config, issues);
+            }
+            Err(why) if why.kind() == ErrorKind::NotFound => {
+                info!("Creating {}", file.display());
+                let config = Self::default();
+                config.save();
+                config
             }
             Err(why) => {
-                warn!("Failed to open config.json: {}", why);
-                default() // TODO: impl ask to initialize
+                warn!("Failed to open {}: {}", file.display(), why);
+                default()
             }
-        }
+        };
+        issues.extend(config.validate());
+        (config, issues)
     }
 
-    /// Apply to config.json
-    pub fn save(&self) {
-        match OpenOptions::new()
-            .write(true)
-            .truncate(true)
-            .open("config.json")
-        {
-            Ok(mut f) => {
-                if let Err(why) = to_writer(&mut f, self) {
-                    warn!("Failed to save config.json: {}", why);
-                }
+    /// Reads config.json and migrates it in memory without writing any file.
+    ///
+    /// The problems found in it are added to `issues`, but the values are not validated.
+    pub(crate) fn read(issues: &mut Vec<ConfigIssue>) -> io::Result<Loaded> {
+        let file = path::config_file();
+        let loaded = match from_reader::<_, Value>(File::open(&file)?) {
+            Ok(value) => migration::migrate(value, issues),
+            Err(why) => {
+                warn!("Failed to parse {}: {}", file.display(), why);
+                issues.push(ConfigIssue::Unreadable(why.to_string()));
+                Loaded::Current(default())
+            }
+        };
+        Ok(loaded)
+    }
+
+    /// Copies config.json to config.`suffix`.json.bak in the same directory.
+    ///
+    /// `suffix` comes from the file, so the characters other than
+    /// ASCII alphanumerics, `.`, `-` and `_` are dropped from it.
+    /// Returns whether the backup succeeded.
+    pub fn backup(suffix: &str) -> bool {
+        let file = path::config_file();
+        let backup = file.with_file_name(backup_name(suffix));
+        match fs::copy(&file, &backup) {
+            Ok(_) => {
+                info!("Backed up config.json to {}", backup.display());
+                true
             }
             Err(why) => {
-                warn!("Failed to open config.json: {}", why)
-                // TODO: impl ask to initialize
+                warn!("Failed to back up config.json: {}", why);
+                false
             }
         }
     }
+
+    /// Open config.json with the default application of the OS.
+    pub fn open_file() {
+        let (program, args): (&str, &[&str]) = if cfg!(target_os = "windows") {
+            ("cmd", &["/C", "start", ""])
+        } else if cfg!(target_os = "macos") {
+            ("open", &[])
+        } else {
+            ("xdg-open", &[])
+        };
+        let file = path::config_file();
+        if let Err(why) = Command::new(program).args(args).arg(&file).spawn() {
+            warn!("Failed to open {}: {}", file.display(), why);
+        }
+    }
+
+    /// Stack height above which the music gets intense (Row)
+    ///
+    /// Always within the board, whatever its height is.
+    pub fn danger_height(&self) -> usize {
+        let height = usize::from(self.board.height);
+        let rows = (height * usize::from(self.danger_ratio) + 50) / 100;
+        rows.clamp(1, height.max(1))
+    }
+
+    /// Apply to config.json
+    pub fn save(&self) {
+        let file = path::config_file();
+        let result = to_vec(self)
+            .map_err(io::Error::from)
+            .and_then(|json| path::write_atomic(&file, &json));
+        if let Err(why) = result {
+            warn!("Failed to save {}: {}", file.display(), why);
+        }
+    }
+}
+
+/// Config edited in the settings screen and shown before it is applied
+///
+/// While it is `Some`, the theme, the layout and the glyphs are drawn with it
+/// instead of `Config`.
+#[derive(Resource, Default)]
+pub struct ConfigPreview(pub Option<Config>);
+
+impl ConfigPreview {
+    /// Returns the previewed config, or `config` if nothing is previewed.
+    pub fn or<'a>(&'a self, config: &'a Config) -> &'a Config {
+        self.0.as_ref().unwrap_or(config)
+    }
+}
+
+/// File name of the backup of config.json, which never leaves the directory
+fn backup_name(suffix: &str) -> String {
+    let suffix: String = suffix
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_'))
+        .collect();
+    format!("config.{}.json.bak", suffix)
 }
 
 impl Default for Config {
     fn default() -> Self {
         Self {
-            version: ConfVer::from_str(env!("CARGO_PKG_VERSION")),
+            version: ConfVer::current_version(),
             vsync: false,
             grid_opacity: 8,
+            ui_scale: 100,
+            theme: String::from("default"),
+            color_blind: ColorBlind::Off,
+            mino_glyphs: false,
+            effects: Effects::default(),
             music_volume: 50.,
+            music_shuffle: false,
+            music_repeat: Repeat::All,
+            adaptive_music: true,
+            danger_ratio: 70,
+            sfx_volume: 50.,
+            sfx_pack: String::from("default"),
             window_mode: WindowModeForConf::Windowed,
+            board: BoardSize::default(),
             handling: Handling::default(),
+            key_bindings: KeyBindings::default(),
+            button_bindings: ButtonBindings::default(),
+            stick_deadzone: 50,
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+/// Version of the config schema
+///
+/// Bump it only when a field is renamed, removed or changes its meaning.
+/// A new field with a default is read from the older configs as is.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
 pub enum ConfVer {
     Invalid,
     #[serde(rename = "v0.1.0")]
     V0_1_0,
+    /// Made ARR fractional.
+    #[serde(rename = "v0.2.0")]
+    V0_2_0,
+    /// Made the danger height a percentage of the board height.
+    #[serde(rename = "v0.3.0")]
+    V0_3_0,
 }
 
 impl ConfVer {
     /// Get the `ConfVer` from a string.
+    #[allow(clippy::should_implement_trait)]
     pub fn from_str(s: &str) -> Self {
         match s {
             "v0.1.0" => ConfVer::V0_1_0,
+            "v0.2.0" => ConfVer::V0_2_0,
+            "v0.3.0" => ConfVer::V0_3_0,
             _ => {
                 error!(
-                    "Unknown config version: {}\nThis client version is `{}` so config file will be initialized.", 
+                    "Unknown config version: {}\nThe config version of this client is `{}` so config.json is read as the current one but not rewritten.",
                     s,
                     Self::current_version()
                 );
@@ -119,7 +293,7 @@ impl ConfVer {
 
     /// Get the current version of the config.
     pub fn current_version() -> Self {
-        Self::from_str(env!("CARGO_PKG_VERSION"))
+        Self::V0_3_0
     }
 }
 
@@ -127,12 +301,14 @@ impl std::fmt::Display for ConfVer {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         match self {
             ConfVer::V0_1_0 => write!(f, "v0.1.0"),
+            ConfVer::V0_2_0 => write!(f, "v0.2.0"),
+            ConfVer::V0_3_0 => write!(f, "v0.3.0"),
             _ => write!(f, "Invalid"),
         }
     }
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
 pub enum WindowModeForConf {
     Windowed,
     Fullscreen,
@@ -160,13 +336,129 @@ impl WindowModeForConf {
     }
 }
 
+/// Visual effects
+///
+/// The effects are only drawn and never delay the game.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct Effects {
+    /// Turns off the moving effects: the collapse, the hard drop trail,
+    /// the board shake and the particles.
+    pub reduced_motion: bool,
+    /// Flash of the cleared lines
+    pub line_clear_flash: bool,
+    /// Fall of the rows above the cleared lines
+    pub collapse: bool,
+    /// Trail of the hard dropped mino
+    pub hard_drop_trail: bool,
+    /// Flash of the locked mino
+    pub lock_flash: bool,
+    /// Shake of the board on big line clears
+    pub board_shake: bool,
+    /// Particles bursting from the cleared lines
+    pub particles: bool,
+}
+
+impl Effects {
+    /// Whether the collapse is drawn.
+    pub fn collapse(&self) -> bool {
+        self.collapse && !self.reduced_motion
+    }
+
+    /// Whether the hard drop trail is drawn.
+    pub fn hard_drop_trail(&self) -> bool {
+        self.hard_drop_trail && !self.reduced_motion
+    }
+
+    /// Whether the board shakes.
+    pub fn board_shake(&self) -> bool {
+        self.board_shake && !self.reduced_motion
+    }
+
+    /// Whether the particles are drawn.
+    pub fn particles(&self) -> bool {
+        self.particles && !self.reduced_motion
+    }
+}
+
+impl Default for Effects {
+    fn default() -> Self {
+        Self {
+            reduced_motion: false,
+            line_clear_flash: true,
+            collapse: true,
+            hard_drop_trail: true,
+            lock_flash: true,
+            board_shake: true,
+            particles: true,
+        }
+    }
+}
+
+/// Size of the board (Cell)
+///
+/// The minoes spawn in the buffer above the visible area.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(default)]
+pub struct BoardSize {
+    /// Number of the columns (4-20)
+    pub width: u8,
+    /// Number of the visible rows (4-40)
+    pub height: u8,
+    /// Number of the hidden rows above the visible area (4-40)
+    pub buffer: u8,
+}
+
+impl Default for BoardSize {
+    fn default() -> Self {
+        Self {
+            width: 10,
+            height: 20,
+            buffer: 20,
+        }
+    }
+}
+
+impl BoardSize {
+    /// Range of `width`
+    pub const WIDTH_RANGE: RangeInclusive<u8> = 4..=20;
+    /// Range of `height`
+    pub const HEIGHT_RANGE: RangeInclusive<u8> = 4..=40;
+    /// Range of `buffer`
+    pub const BUFFER_RANGE: RangeInclusive<u8> = 4..=40;
+
+    /// Whether all the dimensions are in their ranges.
+    pub fn is_valid(&self) -> bool {
+        Self::WIDTH_RANGE.contains(&self.width)
+            && Self::HEIGHT_RANGE.contains(&self.height)
+            && Self::BUFFER_RANGE.contains(&self.buffer)
+    }
+}
+
 /// Control handlings
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
 pub struct Handling {
     /// Delay Auto Shift (Frame)
     pub das: NonZeroU8,
     /// Automatic Repeat Rate (Frame)
-    pub arr: u8,
+    ///
+    /// Can be fractional. `0` moves the mino to the furthest column at once.
+    pub arr: f32,
+    /// Soft Drop Factor (multiplier of the gravity)
+    pub sdf: NonZeroU8,
+    /// Times per second the inputs are processed (60-1000)
+    pub input_rate: u16,
+    /// Whether the charged DAS is kept for the next mino
+    pub das_carry: bool,
+    /// DAS Cut Delay: the auto shift waits after a spawn or a hard drop (Frame)
+    pub dcd: u8,
+    /// Movement while both left and right are held
+    pub both_held: BothHeld,
+    /// Initial Rotation System: rotates the next mino if a rotation is held on spawn
+    pub irs: bool,
+    /// Initial Hold System: holds the next mino if hold is held on spawn
+    pub ihs: bool,
 }
 
 impl Default for Handling {
@@ -174,8 +466,128 @@ impl Default for Handling {
         unsafe {
             Self {
                 das: NonZeroU8::new_unchecked(11),
-                arr: 2,
+                arr: 2.,
+                sdf: NonZeroU8::new_unchecked(20),
+                input_rate: 60,
+                das_carry: true,
+                dcd: 0,
+                both_held: BothHeld::KeepCurrent,
+                irs: false,
+                ihs: false,
             }
         }
     }
 }
+
+/// Movement while both left and right are held
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BothHeld {
+    /// Keep moving to the direction pressed first.
+    KeepCurrent,
+    /// Move to the direction pressed last.
+    PreferLast,
+    /// Stop moving until either is released.
+    Cancel,
+}
+
+impl BothHeld {
+    pub const ALL: [Self; 3] = [Self::KeepCurrent, Self::PreferLast, Self::Cancel];
+}
+
+impl std::fmt::Display for BothHeld {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BothHeld::KeepCurrent => write!(f, "Keep Current"),
+            BothHeld::PreferLast => write!(f, "Prefer Last"),
+            BothHeld::Cancel => write!(f, "Cancel"),
+        }
+    }
+}
+
+/// Repetition of the music
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Repeat {
+    /// Stop after the last track.
+    Off,
+    /// Play the tracks again from the first one.
+    All,
+    /// Play the same track again.
+    One,
+}
+
+impl Repeat {
+    pub const ALL: [Self; 3] = [Self::Off, Self::All, Self::One];
+}
+
+impl std::fmt::Display for Repeat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Repeat::Off => write!(f, "Off"),
+            Repeat::All => write!(f, "All"),
+            Repeat::One => write!(f, "One"),
+        }
+    }
+}
+
+/// Colour-blind palette of the minoes
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ColorBlind {
+    /// Use the colors of the theme.
+    Off,
+    /// For red-weak vision.
+    Protanopia,
+    /// For green-weak vision.
+    Deuteranopia,
+    /// For blue-weak vision.
+    Tritanopia,
+    /// Bright colors far apart in lightness.
+    HighContrast,
+}
+
+impl ColorBlind {
+    pub const ALL: [Self; 5] = [
+        Self::Off,
+        Self::Protanopia,
+        Self::Deuteranopia,
+        Self::Tritanopia,
+        Self::HighContrast,
+    ];
+}
+
+impl std::fmt::Display for ColorBlind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ColorBlind::Off => write!(f, "Off"),
+            ColorBlind::Protanopia => write!(f, "Protanopia"),
+            ColorBlind::Deuteranopia => write!(f, "Deuteranopia"),
+            ColorBlind::Tritanopia => write!(f, "Tritanopia"),
+            ColorBlind::HighContrast => write!(f, "High Contrast"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn backup_name_has_no_separator() {
+        assert_eq!(backup_name("v0.1.0"), "config.v0.1.0.json.bak");
+        assert_eq!(backup_name("../../evil"), "config.....evil.json.bak");
+        assert_eq!(backup_name("a\\b/c"), "config.abc.json.bak");
+    }
+
+    #[test]
+    fn danger_height_is_within_board() {
+        let mut config = Config::default();
+        assert_eq!(config.danger_height(), 14);
+        config.board.height = 4;
+        config.danger_ratio = 1;
+        assert_eq!(config.danger_height(), 1);
+        config.danger_ratio = 100;
+        assert_eq!(config.danger_height(), 4);
+        config.board.height = 40;
+        config.danger_ratio = 70;
+        assert_eq!(config.danger_height(), 28);
+    }
+}
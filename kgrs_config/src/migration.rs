@@ -0,0 +1,430 @@
+//! Migration of config.json written by older versions
+//!
+//! Each older version of the schema has its own struct,
+//! which is converted step by step to the next version up to the current `Config`.
+//! The fields added later have defaults and are read from the older configs as is,
+//! so a new version is only needed when a field is renamed, removed or changes its meaning.
+//!
+//! A config of an unknown version, such as one written by a newer build,
+//! is read as the current one but never rewritten.
+
+use crate::{validation::ConfigIssue, ConfVer, Config, Handling};
+use bevy::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{from_value, Map, Value};
+
+/// Config read from config.json
+pub(crate) enum Loaded {
+    /// The config was written by the current version or can't be migrated.
+    Current(Config),
+    /// The config was migrated from another version.
+    Migrated {
+        /// Version written in the original file.
+        from: String,
+        config: Config,
+    },
+}
+
+/// Config of v0.2.0, whose danger height is in rows
+#[derive(Deserialize)]
+#[serde(default)]
+struct ConfigV0_2_0 {
+    /// Stack height above which the music gets intense (Row)
+    danger_height: u8,
+    #[serde(flatten)]
+    rest: Config,
+}
+
+/// Config of v0.1.0, whose ARR is integral
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct ConfigV0_1_0 {
+    handling: HandlingV0_1_0,
+    #[serde(flatten)]
+    rest: ConfigV0_2_0,
+}
+
+/// Handlings of v0.1.0
+#[derive(Deserialize)]
+#[serde(default)]
+struct HandlingV0_1_0 {
+    /// Automatic Repeat Rate (Frame)
+    arr: u8,
+    #[serde(flatten)]
+    rest: Handling,
+}
+
+impl Default for ConfigV0_2_0 {
+    fn default() -> Self {
+        Self {
+            danger_height: 14,
+            rest: Config::default(),
+        }
+    }
+}
+
+impl Default for HandlingV0_1_0 {
+    fn default() -> Self {
+        Self {
+            arr: 2,
+            rest: Handling::default(),
+        }
+    }
+}
+
+impl From<ConfigV0_1_0> for ConfigV0_2_0 {
+    fn from(old: ConfigV0_1_0) -> Self {
+        let mut config = old.rest;
+        config.rest.handling = Handling {
+            arr: f32::from(old.handling.arr),
+            ..old.handling.rest
+        };
+        config
+    }
+}
+
+impl From<ConfigV0_2_0> for Config {
+    fn from(old: ConfigV0_2_0) -> Self {
+        let board_height = u32::from(old.rest.board.height.max(1));
+        let ratio = (u32::from(old.danger_height) * 100 + board_height / 2) / board_height;
+        Self {
+            version: ConfVer::current_version(),
+            danger_ratio: ratio.clamp(1, 100) as u8,
+            ..old.rest
+        }
+    }
+}
+
+/// Reads the config of any version and migrates it to the current one.
+///
+/// The fields which couldn't be read are added to `issues`.
+/// A config of an unknown version is read as the current one and added to `issues`.
+pub(crate) fn migrate(mut value: Value, issues: &mut Vec<ConfigIssue>) -> Loaded {
+    let version = value
+        .get("version")
+        .and_then(Value::as_str)
+        .unwrap_or("unknown")
+        .to_string();
+
+    let config = match ConfVer::from_str(&version) {
+        ConfVer::V0_3_0 => return Loaded::Current(read_fields(value, issues)),
+        ConfVer::V0_2_0 => read_fields::<ConfigV0_2_0>(value, issues).into(),
+        ConfVer::V0_1_0 => ConfigV0_2_0::from(read_fields::<ConfigV0_1_0>(value, issues)).into(),
+        ConfVer::Invalid => {
+            // The file is left to the player instead of being downgraded.
+            issues.push(ConfigIssue::UnknownVersion(version));
+            if let Some(fields) = value.as_object_mut() {
+                fields.remove("version");
+            }
+            return Loaded::Current(read_fields(value, issues));
+        }
+    };
+    info!(
+        "Migrated config.json from {} to {}",
+        version,
+        ConfVer::current_version()
+    );
+    Loaded::Migrated {
+        from: version,
+        config,
+    }
+}
+
+/// Reads the config as `T` and sets the fields which can't be read to the default.
+pub(crate) fn read_fields<T: DeserializeOwned + Default>(
+    mut value: Value,
+    issues: &mut Vec<ConfigIssue>,
+) -> T {
+    if let Some(fields) = value.as_object_mut() {
+        drop_invalid_fields::<T>(fields, &[], issues);
+    }
+    from_value(value).unwrap_or_else(|why| {
+        warn!("Failed to parse config.json: {}", why);
+        issues.push(ConfigIssue::Unreadable(why.to_string()));
+        T::default()
+    })
+}
+
+/// Removes the fields which can't be read as a part of `T` from the object at `parents`.
+///
+/// An object is looked into to keep its valid fields.
+fn drop_invalid_fields<T: DeserializeOwned>(
+    fields: &mut Map<String, Value>,
+    parents: &[String],
+    issues: &mut Vec<ConfigIssue>,
+) {
+    let keys = fields.keys().cloned().collect::<Vec<_>>();
+    for key in keys {
+        let path = [parents, std::slice::from_ref(&key)].concat();
+        if read_alone::<T>(&path, &fields[&key]).is_ok() {
+            continue;
+        }
+        if let Some(Value::Object(children)) = fields.get_mut(&key) {
+            drop_invalid_fields::<T>(children, &path, issues);
+        }
+        if let Err(why) = read_alone::<T>(&path, &fields[&key]) {
+            let path = path.join(".");
+            warn!("Ignoring `{}` in config.json: {}", path, why);
+            issues.push(ConfigIssue::InvalidField {
+                path,
+                reason: why.to_string(),
+            });
+            fields.remove(&key);
+        }
+    }
+}
+
+/// Reads `T` which only has the field at the path.
+fn read_alone<T: DeserializeOwned>(path: &[String], field: &Value) -> serde_json::Result<T> {
+    let value = path.iter().rev().fold(field.clone(), |value, key| {
+        Value::Object(Map::from_iter([(key.clone(), value)]))
+    });
+    from_value(value)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        binding::KeyBindings, BoardSize, BothHeld, ColorBlind, Effects, Handling, Repeat,
+        WindowModeForConf,
+    };
+    use serde_json::json;
+    use std::num::NonZeroU8;
+
+    fn migrated(value: Value) -> Config {
+        match migrate(value, &mut Vec::new()) {
+            Loaded::Migrated { config, .. } => config,
+            Loaded::Current(_) => panic!("The config was not migrated"),
+        }
+    }
+
+    #[test]
+    fn current_config_is_kept() {
+        let config = Config {
+            vsync: true,
+            grid_opacity: 30,
+            ..default()
+        };
+        let value = serde_json::to_value(&config).unwrap();
+        match migrate(value, &mut Vec::new()) {
+            Loaded::Current(loaded) => assert!(loaded == config),
+            Loaded::Migrated { .. } => panic!("The current config was migrated"),
+        }
+    }
+
+    #[test]
+    fn missing_fields_of_current_config_are_default() {
+        match migrate(
+            json!({ "version": "v0.3.0", "vsync": true }),
+            &mut Vec::new(),
+        ) {
+            Loaded::Current(config) => assert!(
+                config
+                    == Config {
+                        vsync: true,
+                        ..default()
+                    }
+            ),
+            Loaded::Migrated { .. } => panic!("The current config was migrated"),
+        }
+    }
+
+    #[test]
+    fn migrate_older_configs() {
+        let das = NonZeroU8::new(6).unwrap();
+        let key_bindings: KeyBindings = from_value(json!({ "HardDrop": ["W"] })).unwrap();
+
+        // Configs of the older versions and what they are migrated to
+        let cases = [
+            (
+                json!({
+                    "version": "v0.1.0",
+                    "vsync": true,
+                    "grid_opacity": 20,
+                    "music_volume": 14.0,
+                    "window_mode": "BorderlessFullscreen",
+                    "handling": { "das": 6, "arr": 0 }
+                }),
+                Config {
+                    vsync: true,
+                    grid_opacity: 20,
+                    music_volume: 14.,
+                    window_mode: WindowModeForConf::BorderlessFullscreen,
+                    handling: Handling {
+                        das,
+                        arr: 0.,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ),
+            (
+                json!({
+                    "version": "v0.1.0",
+                    "handling": { "das": 6, "arr": 1, "dcd": 2, "both_held": "Cancel" },
+                    "key_bindings": { "HardDrop": ["W"] },
+                    "stick_deadzone": 30
+                }),
+                Config {
+                    handling: Handling {
+                        das,
+                        arr: 1.,
+                        dcd: 2,
+                        both_held: BothHeld::Cancel,
+                        ..default()
+                    },
+                    key_bindings,
+                    stick_deadzone: 30,
+                    ..default()
+                },
+            ),
+            (
+                json!({
+                    "version": "v0.1.0",
+                    "theme": "midnight",
+                    "color_blind": "Tritanopia",
+                    "effects": { "reduced_motion": true },
+                    "music_repeat": "One",
+                    "sfx_pack": "retro"
+                }),
+                Config {
+                    theme: String::from("midnight"),
+                    color_blind: ColorBlind::Tritanopia,
+                    effects: Effects {
+                        reduced_motion: true,
+                        ..default()
+                    },
+                    music_repeat: Repeat::One,
+                    sfx_pack: String::from("retro"),
+                    ..default()
+                },
+            ),
+            (
+                json!({ "version": "v0.2.0", "danger_height": 10 }),
+                Config {
+                    danger_ratio: 50,
+                    ..default()
+                },
+            ),
+            (
+                json!({
+                    "version": "v0.2.0",
+                    "danger_height": 30,
+                    "board": { "width": 10, "height": 40, "buffer": 20 }
+                }),
+                Config {
+                    danger_ratio: 75,
+                    board: BoardSize {
+                        width: 10,
+                        height: 40,
+                        buffer: 20,
+                    },
+                    ..default()
+                },
+            ),
+            (
+                json!({ "version": "v0.2.0", "danger_height": 40 }),
+                Config {
+                    danger_ratio: 100,
+                    ..default()
+                },
+            ),
+            (
+                json!({ "version": "v0.2.0", "handling": { "arr": 0.5 } }),
+                Config {
+                    handling: Handling {
+                        arr: 0.5,
+                        ..default()
+                    },
+                    ..default()
+                },
+            ),
+        ];
+        for (i, (value, expected)) in cases.into_iter().enumerate() {
+            let config = migrated(value);
+            assert!(config.version == ConfVer::current_version(), "case {}", i);
+            assert!(config == expected, "case {}", i);
+        }
+    }
+
+    #[test]
+    fn unknown_version_is_not_migrated() {
+        for value in [
+            json!({ "version": "v9.9.9", "vsync": true, "handling": { "das": 0, "arr": 3 } }),
+            json!({ "vsync": true, "handling": { "das": 0, "arr": 3 } }),
+        ] {
+            let mut issues = Vec::new();
+            match migrate(value, &mut issues) {
+                Loaded::Current(config) => {
+                    assert!(config.vsync);
+                    assert_eq!(config.handling.arr, 3.);
+                    assert_eq!(config.handling.das, Handling::default().das);
+                }
+                Loaded::Migrated { .. } => panic!("The unknown config was migrated"),
+            }
+            assert!(matches!(issues[0], ConfigIssue::UnknownVersion(_)));
+            assert_eq!(issues.len(), 2);
+        }
+    }
+
+    #[test]
+    fn recover_board_field_by_field() {
+        let mut issues = Vec::new();
+        let config: Config = read_fields(
+            json!({
+                "board": { "width": 4, "height": "tall", "buffer": 24 }
+            }),
+            &mut issues,
+        );
+        assert_eq!(config.board.width, 4);
+        assert_eq!(config.board.height, BoardSize::default().height);
+        assert_eq!(config.board.buffer, 24);
+        assert_eq!(issues.len(), 1);
+    }
+
+    #[test]
+    fn recover_effects_field_by_field() {
+        let mut issues = Vec::new();
+        let config: Config = read_fields(
+            json!({
+                "effects": { "reduced_motion": true, "particles": "many" }
+            }),
+            &mut issues,
+        );
+        assert!(config.effects.reduced_motion);
+        assert!(config.effects.particles);
+        assert!(!config.effects.particles());
+        assert_eq!(issues.len(), 1);
+    }
+
+    #[test]
+    fn broken_current_config_keeps_valid_fields() {
+        let mut issues = Vec::new();
+        let value = json!({
+            "version": "v0.3.0",
+            "vsync": true,
+            "grid_opacity": 8,
+            "music_volume": "loud",
+            "window_mode": "Windowed",
+            "handling": {
+                "das": 6,
+                "arr": 0,
+                "sdf": 20,
+                "input_rate": 60,
+                "das_carry": true,
+                "dcd": 0,
+                "both_held": "KeepCurrent"
+            }
+        });
+        match migrate(value, &mut issues) {
+            Loaded::Current(config) => {
+                assert!(config.vsync);
+                assert_eq!(config.handling.das.get(), 6);
+                assert_eq!(config.music_volume, Config::default().music_volume);
+            }
+            Loaded::Migrated { .. } => panic!("The current config was migrated"),
+        }
+        assert_eq!(issues.len(), 1);
+    }
+}
@@ -0,0 +1,161 @@
+//! Directories and files of KaGRiS
+//!
+//! The config is stored in the config directory of the platform
+//! (`$XDG_CONFIG_HOME/kagris` on Linux) and the replays and records in the data directory
+//! (`$XDG_DATA_HOME/kagris` on Linux).
+//! They can be overridden by the `--config-dir`/`--data-dir` flags
+//! or the `KAGRIS_CONFIG_DIR`/`KAGRIS_DATA_DIR` environment variables.
+
+use bevy::prelude::*;
+use directories::ProjectDirs;
+use std::{
+    env, fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Environment variable overriding the config directory
+pub const CONFIG_DIR_ENV: &str = "KAGRIS_CONFIG_DIR";
+/// Environment variable overriding the data directory
+pub const DATA_DIR_ENV: &str = "KAGRIS_DATA_DIR";
+
+/// Name of the config file
+const CONFIG_FILE: &str = "config.json";
+
+/// Reads the command line flags overriding the directories.
+///
+/// Must be called before loading the config.
+pub fn apply_args(args: impl IntoIterator<Item = String>) {
+    for (var, dir) in parse_args(args) {
+        env::set_var(var, dir);
+    }
+}
+
+/// Returns the environment variables to set for the flags.
+fn parse_args(args: impl IntoIterator<Item = String>) -> Vec<(&'static str, String)> {
+    let mut vars = Vec::new();
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let (flag, value) = match arg.split_once('=') {
+            Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+            None => (arg, None),
+        };
+        let var = match flag.as_str() {
+            "--config-dir" => CONFIG_DIR_ENV,
+            "--data-dir" => DATA_DIR_ENV,
+            _ => continue,
+        };
+        match value.or_else(|| args.next()) {
+            Some(dir) => vars.push((var, dir)),
+            None => warn!("Missing the directory after `{}`", flag),
+        }
+    }
+    vars
+}
+
+fn project_dirs() -> Option<ProjectDirs> {
+    ProjectDirs::from("", "", "KaGRiS")
+}
+
+/// Directory of the config
+pub fn config_dir() -> PathBuf {
+    match env::var_os(CONFIG_DIR_ENV) {
+        Some(dir) => dir.into(),
+        None => project_dirs()
+            .map(|dirs| dirs.config_dir().to_path_buf())
+            .unwrap_or_default(),
+    }
+}
+
+/// Directory of the replays and records
+pub fn data_dir() -> PathBuf {
+    match env::var_os(DATA_DIR_ENV) {
+        Some(dir) => dir.into(),
+        None => project_dirs()
+            .map(|dirs| dirs.data_dir().to_path_buf())
+            .unwrap_or_default(),
+    }
+}
+
+/// Path of config.json
+pub fn config_file() -> PathBuf {
+    config_dir().join(CONFIG_FILE)
+}
+
+/// Creates the config and data directories if they don't exist.
+pub fn create_dirs() {
+    for dir in [config_dir(), data_dir()] {
+        if !dir.exists() {
+            info!("Creating {}", dir.display());
+        }
+        if let Err(why) = fs::create_dir_all(&dir) {
+            warn!("Failed to create {}: {}", dir.display(), why);
+        }
+    }
+}
+
+/// Moves config.json in the working directory used by older versions
+/// to the config directory.
+pub(crate) fn import_legacy_config() {
+    let legacy = Path::new(CONFIG_FILE);
+    let file = config_file();
+    if legacy.is_file() && !file.exists() {
+        info!("Moving {} to {}", legacy.display(), file.display());
+        // `rename` fails across file systems, so fall back to copying.
+        let result = fs::rename(legacy, &file)
+            .or_else(|_| fs::copy(legacy, &file).and_then(|_| fs::remove_file(legacy)));
+        if let Err(why) = result {
+            warn!("Failed to move {}: {}", legacy.display(), why);
+        }
+    }
+}
+
+/// Writes the contents to a temporary file and renames it to `path`
+/// so that the file is never left half-written.
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(".tmp");
+    fs::write(&tmp, contents)?;
+    fs::rename(&tmp, path)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        args.iter().map(|arg| arg.to_string()).collect()
+    }
+
+    #[test]
+    fn parse_dir_flags() {
+        let vars = parse_args(args(&[
+            "kagris",
+            "--config-dir",
+            "/tmp/conf",
+            "--data-dir=/tmp/data",
+            "--unknown",
+        ]));
+        assert_eq!(
+            vars,
+            [
+                (CONFIG_DIR_ENV, "/tmp/conf".to_string()),
+                (DATA_DIR_ENV, "/tmp/data".to_string())
+            ]
+        );
+        assert!(parse_args(args(&["kagris", "--config-dir"])).is_empty());
+    }
+
+    #[test]
+    fn write_atomic_creates_and_replaces_file() {
+        let dir = env::temp_dir().join(format!("kagris-test-{}", std::process::id()));
+        let path = dir.join("config.json");
+        write_atomic(&path, b"old").unwrap();
+        write_atomic(&path, b"new").unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"new");
+        assert_eq!(fs::read_dir(&dir).unwrap().count(), 1);
+        fs::remove_dir_all(dir).unwrap();
+    }
+}
@@ -0,0 +1,297 @@
+//! Profiles of the players sharing the machine
+//!
+//! A profile holds the settings of a player and the records.
+//! The settings of the selected profile are copied to `Config`
+//! and the changes of `Config` are copied back to the profile.
+
+use crate::{
+    binding::{ButtonBindings, KeyBindings},
+    path, ColorBlind, Config, Effects, Handling,
+};
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{from_slice, to_vec};
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Maximum number of characters of a profile name
+pub const MAX_NAME_LEN: usize = 16;
+
+/// Directory of the profiles in the config directory
+const PROFILES_DIR: &str = "profiles";
+
+/// The selected profile.
+/// `None` if the player plays as a guest.
+#[derive(Resource, Default)]
+pub struct ActiveProfile(pub Option<Profile>);
+
+/// Settings and records of a player
+#[derive(Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Profile {
+    /// Name of the player
+    pub name: String,
+    /// Control handlings
+    pub handling: Handling,
+    /// Keys bound to the actions
+    pub key_bindings: KeyBindings,
+    /// Gamepad buttons bound to the actions
+    pub button_bindings: ButtonBindings,
+    /// Percentage of the stick tilt to be read as a direction (0-90)
+    pub stick_deadzone: u8,
+    /// Opacity percentage of the grid (0-100)
+    pub grid_opacity: u8,
+    /// Size percentage of the board, the HUD and the menus (50-150)
+    pub ui_scale: u8,
+    /// Name of the theme in `assets/themes`
+    pub theme: String,
+    /// Palette of the minoes replacing the theme colors
+    pub color_blind: ColorBlind,
+    /// Whether to draw a glyph of the kind on each block
+    pub mino_glyphs: bool,
+    /// Visual effects of the game
+    pub effects: Effects,
+    /// Records of the games
+    pub records: Records,
+}
+
+impl Default for Profile {
+    fn default() -> Self {
+        let config = Config::default();
+        Self {
+            name: String::new(),
+            handling: config.handling,
+            key_bindings: config.key_bindings,
+            button_bindings: config.button_bindings,
+            stick_deadzone: config.stick_deadzone,
+            grid_opacity: config.grid_opacity,
+            ui_scale: config.ui_scale,
+            theme: config.theme,
+            color_blind: config.color_blind,
+            mino_glyphs: config.mino_glyphs,
+            effects: config.effects,
+            records: Records::default(),
+        }
+    }
+}
+
+/// Records of a player
+#[derive(Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(default)]
+pub struct Records {
+    /// Best score of each game mode
+    pub best_scores: HashMap<String, u64>,
+    /// Results of the PC practice of each opener
+    pub pc_practice: HashMap<String, PcRecord>,
+}
+
+/// Results of the PC attempts for an opener
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub struct PcRecord {
+    pub attempts: u32,
+    pub successes: u32,
+}
+
+impl Profile {
+    /// Creates a profile with the current settings.
+    pub fn new(name: &str, config: &Config) -> Self {
+        let mut profile = Self {
+            name: name.to_string(),
+            ..default()
+        };
+        profile.update_from(config);
+        profile
+    }
+
+    /// Copies the settings of the profile to the config.
+    pub fn apply_to(&self, config: &mut Config) {
+        config.handling = self.handling;
+        config.key_bindings = self.key_bindings.clone();
+        config.button_bindings = self.button_bindings.clone();
+        config.stick_deadzone = self.stick_deadzone;
+        config.grid_opacity = self.grid_opacity;
+        config.ui_scale = self.ui_scale;
+        config.theme = self.theme.clone();
+        config.color_blind = self.color_blind;
+        config.mino_glyphs = self.mino_glyphs;
+        config.effects = self.effects;
+        config.validate();
+    }
+
+    /// Copies the settings of the config to the profile.
+    pub fn update_from(&mut self, config: &Config) {
+        self.handling = config.handling;
+        self.key_bindings = config.key_bindings.clone();
+        self.button_bindings = config.button_bindings.clone();
+        self.stick_deadzone = config.stick_deadzone;
+        self.grid_opacity = config.grid_opacity;
+        self.ui_scale = config.ui_scale;
+        self.theme = config.theme.clone();
+        self.color_blind = config.color_blind;
+        self.mino_glyphs = config.mino_glyphs;
+        self.effects = config.effects;
+    }
+
+    /// Whether the settings of the profile are same as the config.
+    pub fn is_applied_to(&self, config: &Config) -> bool {
+        self.handling == config.handling
+            && self.key_bindings == config.key_bindings
+            && self.button_bindings == config.button_bindings
+            && self.stick_deadzone == config.stick_deadzone
+            && self.grid_opacity == config.grid_opacity
+            && self.ui_scale == config.ui_scale
+            && self.theme == config.theme
+            && self.color_blind == config.color_blind
+            && self.mino_glyphs == config.mino_glyphs
+            && self.effects == config.effects
+    }
+
+    /// Returns the names of the saved profiles in alphabetical order.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<_> = match fs::read_dir(profiles_dir()) {
+            Ok(entries) => entries
+                .filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    if path.extension()? != "json" {
+                        return None;
+                    }
+                    Some(path.file_stem()?.to_str()?.to_string())
+                })
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names
+    }
+
+    /// Loads the saved profile.
+    pub fn load(name: &str) -> io::Result<Self> {
+        Self::import(&profile_file(name))
+    }
+
+    /// Saves the profile to the profiles directory.
+    pub fn save(&self) {
+        if let Err(why) = self.export(&profile_file(&self.name)) {
+            warn!("Failed to save profile {}: {}", self.name, why);
+        }
+    }
+
+    /// Deletes the saved profile.
+    pub fn delete(name: &str) -> io::Result<()> {
+        fs::remove_file(profile_file(name))
+    }
+
+    /// Reads a profile from the single file.
+    pub fn import(file: &Path) -> io::Result<Self> {
+        let profile: Self = from_slice(&fs::read(file)?)?;
+        validate_name(&profile.name)
+            .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))?;
+        Ok(profile)
+    }
+
+    /// Writes the profile to a single file.
+    pub fn export(&self, file: &Path) -> io::Result<()> {
+        path::write_atomic(file, &to_vec(self)?)
+    }
+}
+
+/// Checks whether the name can be used as a profile name and a file name.
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        Err(String::from("The name is empty"))
+    } else if MAX_NAME_LEN < name.chars().count() {
+        Err(format!(
+            "The name is longer than {} characters",
+            MAX_NAME_LEN
+        ))
+    } else if !name
+        .chars()
+        .all(|c| c.is_alphanumeric() || matches!(c, ' ' | '-' | '_'))
+    {
+        Err(String::from(
+            "The name can only contain letters, digits, spaces, `-` and `_`",
+        ))
+    } else {
+        Ok(())
+    }
+}
+
+fn profiles_dir() -> PathBuf {
+    path::config_dir().join(PROFILES_DIR)
+}
+
+fn profile_file(name: &str) -> PathBuf {
+    profiles_dir().join(format!("{}.json", name))
+}
+
+/// Default file to export the profile to, in the data directory.
+pub fn export_file(name: &str) -> PathBuf {
+    path::data_dir().join(format!("{}.profile.json", name))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::env;
+
+    #[test]
+    fn settings_are_copied_between_profile_and_config() {
+        let mut config = Config::default();
+        let mut profile = Profile::new("Player", &config);
+        assert!(profile.is_applied_to(&config));
+
+        profile.grid_opacity = 40;
+        profile.handling.arr = 0.;
+        profile.theme = String::from("dark");
+        profile.color_blind = ColorBlind::Tritanopia;
+        profile.effects.particles = false;
+        assert!(!profile.is_applied_to(&config));
+        profile.apply_to(&mut config);
+        assert_eq!(config.grid_opacity, 40);
+        assert_eq!(config.handling.arr, 0.);
+        assert_eq!(config.theme, "dark");
+        assert_eq!(config.color_blind, ColorBlind::Tritanopia);
+        assert!(!config.effects.particles);
+
+        config.grid_opacity = 10;
+        config.ui_scale = 120;
+        config.mino_glyphs = !config.mino_glyphs;
+        profile.update_from(&config);
+        assert_eq!(profile.grid_opacity, 10);
+        assert_eq!(profile.ui_scale, 120);
+        assert!(profile.is_applied_to(&config));
+    }
+
+    #[test]
+    fn export_and_import_profile() {
+        let mut profile = Profile::new("Player 1", &Config::default());
+        profile
+            .records
+            .best_scores
+            .insert(String::from("Free Play"), 12345);
+        profile.records.pc_practice.insert(
+            String::from("PCO"),
+            PcRecord {
+                attempts: 4,
+                successes: 3,
+            },
+        );
+
+        let file = env::temp_dir().join(format!("kagris-profile-{}.json", std::process::id()));
+        profile.export(&file).unwrap();
+        let imported = Profile::import(&file).unwrap();
+        fs::remove_file(file).unwrap();
+        assert!(imported == profile);
+    }
+
+    #[test]
+    fn profile_names() {
+        assert!(validate_name("Rinrin_0413").is_ok());
+        assert!(validate_name("  ").is_err());
+        assert!(validate_name("../config").is_err());
+        assert!(validate_name("a very long player name").is_err());
+    }
+}
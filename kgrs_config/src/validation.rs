@@ -0,0 +1,148 @@
+//! Validation of the config values
+
+use crate::{BoardSize, ConfVer, Config};
+use bevy::prelude::*;
+use std::{fmt, ops::RangeInclusive};
+
+/// Problems found in config.json on startup
+#[derive(Resource, Default)]
+pub struct ConfigIssues(pub Vec<ConfigIssue>);
+
+/// Problem found in config.json
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ConfigIssue {
+    /// The file couldn't be parsed at all so every field was reset to the default.
+    Unreadable(String),
+    /// The version is unknown, such as one of a newer build, so the file is not rewritten.
+    UnknownVersion(String),
+    /// The field couldn't be read or was out of range so it was reset to the default.
+    InvalidField {
+        /// Path of the field such as `handling.das`.
+        path: String,
+        reason: String,
+    },
+}
+
+impl fmt::Display for ConfigIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigIssue::Unreadable(why) => write!(f, "Unreadable file: {}", why),
+            ConfigIssue::UnknownVersion(version) => write!(
+                f,
+                "Unknown version `{}`: the file is kept and read as `{}`",
+                version,
+                ConfVer::current_version()
+            ),
+            ConfigIssue::InvalidField { path, reason } => write!(f, "`{}`: {}", path, reason),
+        }
+    }
+}
+
+impl Config {
+    /// Resets the fields out of range to the default.
+    ///
+    /// Returns the reset fields.
+    pub fn validate(&mut self) -> Vec<ConfigIssue> {
+        let default = Self::default();
+        let mut issues = Vec::new();
+
+        if 100 < self.grid_opacity {
+            issues.push(out_of_range("grid_opacity", self.grid_opacity, "0-100"));
+            self.grid_opacity = default.grid_opacity;
+        }
+        if !(50..=150).contains(&self.ui_scale) {
+            issues.push(out_of_range("ui_scale", self.ui_scale, "50-150"));
+            self.ui_scale = default.ui_scale;
+        }
+        if !(0. ..=100.).contains(&self.music_volume) {
+            issues.push(out_of_range("music_volume", self.music_volume, "0-100"));
+            self.music_volume = default.music_volume;
+        }
+        if !(1..=100).contains(&self.danger_ratio) {
+            issues.push(out_of_range("danger_ratio", self.danger_ratio, "1-100"));
+            self.danger_ratio = default.danger_ratio;
+        }
+        if !(0. ..=100.).contains(&self.sfx_volume) {
+            issues.push(out_of_range("sfx_volume", self.sfx_volume, "0-100"));
+            self.sfx_volume = default.sfx_volume;
+        }
+        if !BoardSize::WIDTH_RANGE.contains(&self.board.width) {
+            let range = range_text(&BoardSize::WIDTH_RANGE);
+            issues.push(out_of_range("board.width", self.board.width, &range));
+            self.board.width = default.board.width;
+        }
+        if !BoardSize::HEIGHT_RANGE.contains(&self.board.height) {
+            let range = range_text(&BoardSize::HEIGHT_RANGE);
+            issues.push(out_of_range("board.height", self.board.height, &range));
+            self.board.height = default.board.height;
+        }
+        if !BoardSize::BUFFER_RANGE.contains(&self.board.buffer) {
+            let range = range_text(&BoardSize::BUFFER_RANGE);
+            issues.push(out_of_range("board.buffer", self.board.buffer, &range));
+            self.board.buffer = default.board.buffer;
+        }
+        if !(0. ..=255.).contains(&self.handling.arr) {
+            issues.push(out_of_range("handling.arr", self.handling.arr, "0-255"));
+            self.handling.arr = default.handling.arr;
+        }
+        if !(60..=1000).contains(&self.handling.input_rate) {
+            issues.push(out_of_range(
+                "handling.input_rate",
+                self.handling.input_rate,
+                "60-1000",
+            ));
+            self.handling.input_rate = default.handling.input_rate;
+        }
+        if 90 < self.stick_deadzone {
+            issues.push(out_of_range("stick_deadzone", self.stick_deadzone, "0-90"));
+            self.stick_deadzone = default.stick_deadzone;
+        }
+
+        for issue in &issues {
+            warn!("Invalid config: {}", issue);
+        }
+        issues
+    }
+}
+
+fn out_of_range(path: &str, value: impl fmt::Display, range: &str) -> ConfigIssue {
+    ConfigIssue::InvalidField {
+        path: path.to_string(),
+        reason: format!("{} is out of range ({})", value, range),
+    }
+}
+
+/// Formats the range like `4-20`.
+fn range_text(range: &RangeInclusive<u8>) -> String {
+    format!("{}-{}", range.start(), range.end())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_out_of_range_fields_are_reset() {
+        let mut config = Config {
+            vsync: true,
+            grid_opacity: 150,
+            music_volume: -1.,
+            ..default()
+        };
+        let issues = config.validate();
+        let paths: Vec<_> = issues
+            .iter()
+            .map(|issue| match issue {
+                ConfigIssue::InvalidField { path, .. } => path.as_str(),
+                _ => "",
+            })
+            .collect();
+        assert_eq!(paths, ["grid_opacity", "music_volume"]);
+
+        let default = Config::default();
+        assert!(config.vsync);
+        assert_eq!(config.grid_opacity, default.grid_opacity);
+        assert_eq!(config.music_volume, default.music_volume);
+        assert!(config.validate().is_empty());
+    }
+}
@@ -14,6 +14,19 @@ pub const GRID_COL: Color = Color::rgb(1., 1., 1.);
 /// Frame color of the board (#e6d5b8)
 pub const FRAME_COL: Color = Color::rgb(0.9019, 0.8392, 0.7216);
 
+/// Text color of the HUD (#e6d5b8)
+pub const HUD_TEXT_COL: Color = Color::rgb(0.9019, 0.8392, 0.7216);
+
+/// Colors of each letter of the logo "KaGRiS"
+pub const LOGO_COLS: [Color; 6] = [
+    Color::RED,
+    Color::FUCHSIA,
+    Color::YELLOW,
+    Color::GREEN,
+    Color::CYAN,
+    Color::BLUE,
+];
+
 pub mod mino_color {
     //! Color constants for the minos
 
@@ -3,9 +3,6 @@
 /// The board height as a percentage of the window height.
 pub const BOARD_HEIGHT_RATIO: f32 = 0.797;
 
-/// The board width as a percentage of the window HEIGHT.
-pub const BOARD_WIDTH_RATIO: f32 = BOARD_HEIGHT_RATIO / 2.;
-
 /// Board Y-offset percentage of the window height.
 ///
 /// # Examples
@@ -15,8 +12,29 @@ pub const BOARD_WIDTH_RATIO: f32 = BOARD_HEIGHT_RATIO / 2.;
 /// - 1.0: the board bottom is at the window top.
 pub const BOARD_OFFSET_RATIO_Y: f32 = 0.067;
 
-/// The thickness of the grids.
-pub const GRID_THICKNESS: f32 = 2.;
+/// The thickness of the grids as a percentage of the cell size.
+pub const GRID_THICKNESS_RATIO: f32 = 0.07;
+
+/// The thickness of the frame of the board as a percentage of the cell size.
+pub const FRAME_THICKNESS_RATIO: f32 = 0.175;
+
+/// The width of the hold and the next queue in cells.
+pub const SIDE_PANEL_WIDTH: f32 = 5.;
+
+/// The height of the hold in cells.
+pub const HOLD_HEIGHT: f32 = 3.;
+
+/// The height of the next queue in cells.
+pub const QUEUE_HEIGHT: f32 = 15.;
+
+/// The font size of the HUD as a percentage of the cell size.
+pub const HUD_FONT_SIZE_RATIO: f32 = 0.8;
+
+/// The margin between the board and the HUD as a percentage of the cell size.
+pub const HUD_MARGIN_RATIO: f32 = 0.75;
+
+/// The font size of the menus as a percentage of the window height.
+pub const MENU_FONT_SIZE_RATIO: f32 = 0.045;
 
-/// The thickness of the frame of the board.
-pub const FRAME_THICKNESS: f32 = 5.;
+/// The font size of the logo as a percentage of the window height.
+pub const LOGO_FONT_SIZE_RATIO: f32 = 0.16;
@@ -1,157 +1,279 @@
-use crate::mino::MinoData;
-use bevy::{prelude::*, sprite::MaterialMesh2dBundle, window::WindowResized};
+use crate::{event::ResetGameEvent, layout::Layout, mino::MinoData, theme::Theme};
+use bevy::{
+    prelude::*,
+    sprite::{MaterialMesh2dBundle, Mesh2dHandle},
+};
 use kgrs_config::Config;
-use kgrs_const::{color::*, dimension::*};
+use kgrs_const::dimension::*;
+
+/// Size of a cell on the board
+///
+/// The board is drawn in cells and scaled to the window by `Layout::cell_size`.
+pub(crate) const CELL_SIZE: f32 = 1.;
 
 pub struct BoardPlugin;
 
 impl Plugin for BoardPlugin {
     fn build(&self, app: &mut App) {
-        app.add_startup_system(setup_board).add_system(adjust_board);
+        app.add_startup_system(setup_board)
+            .add_system(resize_board)
+            .add_system(apply_layout)
+            .add_system(apply_grid_opacity);
     }
 }
 
 /// The board
 #[derive(Component)]
-pub(crate) struct Board {
+pub struct Board {
     /// The data of the board.
     pub(crate) data: MinoData,
-    /// Initial width of the board.
-    pub(crate) width: f32,
-    /// Initial height of the board.
-    pub(crate) height: f32,
+}
+
+impl Board {
+    /// Returns the size of the visible area (Cell).
+    pub(crate) fn size(&self) -> Vec2 {
+        let size = self.data.size();
+        Vec2::new(size.width as f32, size.height as f32)
+    }
+
+    /// Returns the center of the cell (Column, Row) relative to the board center.
+    pub(crate) fn cell_translation(&self, cell: UVec2) -> Vec2 {
+        let size = self.size();
+        Vec2::new(
+            -size.x / 2. + (cell.x as f32 + 0.5) * CELL_SIZE,
+            // The visible area starts below the buffer.
+            size.y / 2. - (cell.y as f32 - self.data.buffer() as f32 + 0.5) * CELL_SIZE,
+        )
+    }
 }
 
 /// The grid of the board
 #[derive(Component)]
-struct Grid;
+pub struct Grid;
+
+/// The frame of the board
+#[derive(Component)]
+pub(crate) struct Frame;
 
 /// Setups the board
 fn setup_board(
     mut cmds: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
-    windows: ResMut<Windows>,
+    layout: Res<Layout>,
+    config: Res<Config>,
+    theme: Res<Theme>,
 ) {
     info!("Setting up board");
-    let window_height = windows.get_primary().unwrap().height();
-    let board_width = window_height * BOARD_WIDTH_RATIO;
-    let board_height = window_height * BOARD_HEIGHT_RATIO;
+    let board = Board {
+        data: MinoData::new(config.board),
+    };
+    let size = board.size();
 
     cmds.spawn(MaterialMesh2dBundle {
-        mesh: meshes
-            .add(Mesh::from(shape::Quad {
-                size: Vec2::new(board_width, board_height),
-                ..default()
-            }))
-            .into(),
-        material: materials.add(ColorMaterial::from(BOARD_COL)),
+        mesh: meshes.add(board_mesh(size)).into(),
+        material: materials.add(ColorMaterial::from(theme.board)),
+        transform: board_transform(&layout),
         ..default()
     })
-    .insert(Board {
-        data: MinoData::empty(),
-        width: board_width,
-        height: board_height,
-    })
-    // Grids
+    .insert(board)
     .with_children(|c| {
-        let opac = Config::load().grid_opacity;
-        if 0 < opac {
-            let mut draw_grid = |is_horiz: bool| {
-                let (board_len, grid_num, size) = if is_horiz {
-                    (board_height, 20, Vec2::new(board_width, GRID_THICKNESS))
-                } else {
-                    (board_width, 10, Vec2::new(GRID_THICKNESS, board_height))
-                };
-
-                for i in 1..grid_num {
-                    let mut p = board_len * i as f32 / grid_num as f32;
-                    if board_len / 2. <= p {
-                        p = (p - board_len / 2.) * -1.;
-                    }
-                    let offset = if is_horiz {
-                        Vec2::new(0., p)
-                    } else {
-                        Vec2::new(p, 0.)
-                    };
-                    c.spawn(MaterialMesh2dBundle {
-                        mesh: meshes
-                            .add(Mesh::from(shape::Quad { size, ..default() }))
-                            .into(),
-                        material: materials.add(ColorMaterial::from(Color::rgba(
-                            GRID_COL.r(),
-                            GRID_COL.g(),
-                            GRID_COL.b(),
-                            opac as f32 / 100.,
-                        ))),
-                        transform: Transform::from_xyz(offset.x, offset.y, 0.1),
-                        ..default()
-                    })
-                    .insert(Grid);
-                }
-            };
+        spawn_grid_and_frame(
+            c,
+            size,
+            config.grid_opacity,
+            &theme,
+            &mut meshes,
+            &mut materials,
+        );
+    });
+}
 
-            draw_grid(true);
-            draw_grid(false);
-
-            // Frame
-
-            let half_frame_thick = FRAME_THICKNESS / 2.;
-            let double_frame_thick = FRAME_THICKNESS * 2.;
-
-            for (offset, is_horiz) in [
-                //(board_height / 2. +half_frame_thick, true),  // Top
-                (-board_height / 2. - half_frame_thick, true), // Bottom
-                (board_width / 2. + half_frame_thick, false),  // Right
-                (-board_width / 2. - half_frame_thick, false), // Left
-            ] {
-                // Corners will be broken so added thickness to frame length.
-                let (size, offset) = if is_horiz {
-                    (
-                        Vec2::new(board_width + double_frame_thick, FRAME_THICKNESS),
-                        Vec2::new(0., offset),
-                    )
-                } else {
-                    (
-                        Vec2::new(FRAME_THICKNESS, board_height + double_frame_thick),
-                        Vec2::new(offset, 0.),
-                    )
-                };
-                c.spawn((MaterialMesh2dBundle {
-                    mesh: meshes
-                        .add(Mesh::from(shape::Quad { size, ..default() }))
-                        .into(),
-                    material: materials.add(ColorMaterial::from(FRAME_COL)),
-                    transform: Transform::from_xyz(offset.x, offset.y, 0.2),
-                    ..default()
-                },));
-            }
-        }
+/// Rebuilds the board and restarts the game when the board size is changed.
+#[allow(clippy::too_many_arguments)]
+fn resize_board(
+    mut cmds: Commands,
+    config: Res<Config>,
+    theme: Res<Theme>,
+    mut board_query: Query<(Entity, &mut Board, &mut Mesh2dHandle)>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut reset_writer: EventWriter<ResetGameEvent>,
+) {
+    if !config.is_changed() {
+        return;
+    }
+    let (entity, mut board, mut mesh) = board_query.single_mut();
+    if board.data.size() == config.board {
+        return;
+    }
+    info!(
+        "Resizing board to {}x{} with {} buffer rows",
+        config.board.width, config.board.height, config.board.buffer
+    );
+    board.data = MinoData::new(config.board);
+    let size = board.size();
+    mesh.0 = meshes.add(board_mesh(size));
+
+    // The minoes on the board are despawned with the grids.
+    cmds.entity(entity).despawn_descendants();
+    cmds.entity(entity).with_children(|c| {
+        spawn_grid_and_frame(
+            c,
+            size,
+            config.grid_opacity,
+            &theme,
+            &mut meshes,
+            &mut materials,
+        );
     });
+    reset_writer.send(ResetGameEvent { keep_seed: false });
+}
+
+/// Returns the mesh of the visible area of the board of the size (Cell).
+fn board_mesh(size: Vec2) -> Mesh {
+    Mesh::from(shape::Quad {
+        size: size * CELL_SIZE,
+        ..default()
+    })
+}
+
+/// Spawns the grids and the frame of the board of the size (Cell).
+///
+/// `c` must be the children of the board.
+fn spawn_grid_and_frame(
+    c: &mut ChildBuilder,
+    size: Vec2,
+    grid_opacity: u8,
+    theme: &Theme,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    let (columns, rows) = (size.x as usize, size.y as usize);
+    let board_width = size.x * CELL_SIZE;
+    let board_height = size.y * CELL_SIZE;
+    let grid_thickness = CELL_SIZE * GRID_THICKNESS_RATIO;
+    let frame_thickness = CELL_SIZE * FRAME_THICKNESS_RATIO;
+
+    // Grids are spawned even if invisible to change the opacity later.
+    let mut draw_grid = |is_horiz: bool| {
+        let (board_len, grid_num, size) = if is_horiz {
+            (board_height, rows, Vec2::new(board_width, grid_thickness))
+        } else {
+            (
+                board_width,
+                columns,
+                Vec2::new(grid_thickness, board_height),
+            )
+        };
+
+        for i in 1..grid_num {
+            let p = board_len * i as f32 / grid_num as f32 - board_len / 2.;
+            let offset = if is_horiz {
+                Vec2::new(0., p)
+            } else {
+                Vec2::new(p, 0.)
+            };
+            c.spawn(MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(Mesh::from(shape::Quad { size, ..default() }))
+                    .into(),
+                material: materials.add(ColorMaterial::from(grid_color(theme, grid_opacity))),
+                transform: Transform::from_xyz(offset.x, offset.y, 0.1),
+                ..default()
+            })
+            .insert(Grid);
+        }
+    };
+
+    draw_grid(true);
+    draw_grid(false);
+
+    // Frame
+
+    let half_frame_thick = frame_thickness / 2.;
+    let double_frame_thick = frame_thickness * 2.;
+
+    for (offset, is_horiz) in [
+        //(board_height / 2. +half_frame_thick, true),  // Top
+        (-board_height / 2. - half_frame_thick, true), // Bottom
+        (board_width / 2. + half_frame_thick, false),  // Right
+        (-board_width / 2. - half_frame_thick, false), // Left
+    ] {
+        // Corners will be broken so added thickness to frame length.
+        let (size, offset) = if is_horiz {
+            (
+                Vec2::new(board_width + double_frame_thick, frame_thickness),
+                Vec2::new(0., offset),
+            )
+        } else {
+            (
+                Vec2::new(frame_thickness, board_height + double_frame_thick),
+                Vec2::new(offset, 0.),
+            )
+        };
+        c.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes
+                    .add(Mesh::from(shape::Quad { size, ..default() }))
+                    .into(),
+                material: materials.add(ColorMaterial::from(theme.frame)),
+                transform: Transform::from_xyz(offset.x, offset.y, 0.2),
+                ..default()
+            },
+            Frame,
+        ));
+    }
 }
 
-/// Resizes and repositions the board when the window is resized.
-fn adjust_board(
-    mut resize_reader: EventReader<WindowResized>,
-    mut query: Query<(&Board, &mut Transform)>,
+/// Returns the grid color of the theme with the opacity percentage (0-100).
+fn grid_color(theme: &Theme, opacity: u8) -> Color {
+    Color::rgba(
+        theme.grid.r(),
+        theme.grid.g(),
+        theme.grid.b(),
+        opacity as f32 / 100.,
+    )
+}
+
+/// Applies the grid opacity when the config is changed.
+fn apply_grid_opacity(
+    config: Res<Config>,
+    theme: Res<Theme>,
+    grid_query: Query<&Handle<ColorMaterial>, With<Grid>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    if config.is_changed() && !config.is_added() {
+        set_grid_opacity(config.grid_opacity, &theme, &grid_query, &mut materials);
+    }
+}
+
+/// Changes the opacity percentage (0-100) of the existing grids.
+pub fn set_grid_opacity(
+    opacity: u8,
+    theme: &Theme,
+    grid_query: &Query<&Handle<ColorMaterial>, With<Grid>>,
+    materials: &mut Assets<ColorMaterial>,
 ) {
-    for window in resize_reader.iter() {
-        for (board, mut tf) in query.iter_mut() {
-            tf.scale = Vec3::new(
-                window.height * BOARD_WIDTH_RATIO / board.width,
-                window.height * BOARD_HEIGHT_RATIO / board.height,
-                1.0,
-            );
-            // `Transform.scale` is relative ratio from the initial size
-            // so don't update `board.width` and `board.height`.
-
-            // It is: initial_height * relative_ratio = current_true_height
-            let true_board_height = board.height * tf.scale[1];
-            // Position the board bottom at the window bottom.
-            let pos_bottom = -(window.height - true_board_height) / 2. + GRID_THICKNESS * 2.;
-            // Offset based on `BOARD_OFFSET_RATIO_Y`.
-            let offset = window.height * BOARD_OFFSET_RATIO_Y;
-            tf.translation[1] = pos_bottom + offset;
+    for handle in grid_query.iter() {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = grid_color(theme, opacity);
         }
     }
 }
+
+/// Returns the transform placing the board drawn in cells on the layout.
+fn board_transform(layout: &Layout) -> Transform {
+    Transform::from_translation(layout.board_center.extend(0.))
+        .with_scale(Vec2::splat(layout.cell_size).extend(1.))
+}
+
+/// Resizes and repositions the board when the layout is changed.
+fn apply_layout(layout: Res<Layout>, mut query: Query<&mut Transform, With<Board>>) {
+    if !layout.is_changed() {
+        return;
+    }
+    for mut tf in query.iter_mut() {
+        *tf = board_transform(&layout);
+    }
+}
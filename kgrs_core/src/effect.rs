@@ -0,0 +1,434 @@
+//! Visual effects of the game
+//!
+//! The effects only follow the game events and are drawn over the board,
+//! so the board data is already updated when they start and the game never waits for them.
+//! Each effect can be turned off in `Config::effects`.
+
+use crate::{
+    board::{Board, CELL_SIZE},
+    event::{HardDropEvent, LineClearEvent, LockEvent},
+    layout::Layout,
+    mino::control::place_mino,
+    theme::Theme,
+};
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use kgrs_config::Config;
+use kgrs_util::state::AppState;
+use rand::{thread_rng, Rng};
+use std::f32::consts::TAU;
+
+/// Duration of the flash of the cleared lines (Second)
+const LINE_FLASH_TIME: f32 = 0.25;
+
+/// Duration of the flash of the locked mino (Second)
+const LOCK_FLASH_TIME: f32 = 0.15;
+
+/// Duration of the hard drop trail (Second)
+const TRAIL_TIME: f32 = 0.2;
+
+/// Delay before the rows above the cleared lines start to fall (Second)
+const COLLAPSE_DELAY: f32 = 0.1;
+
+/// Duration of the fall of the rows above the cleared lines (Second)
+const COLLAPSE_TIME: f32 = 0.12;
+
+/// Duration of the board shake (Second)
+const SHAKE_TIME: f32 = 0.35;
+
+/// Number of swings of the board per second
+const SHAKE_FREQUENCY: f32 = 30.;
+
+/// Lifetime of the particles (Second)
+const PARTICLE_TIME: f32 = 0.6;
+
+/// Number of particles per cleared line
+const PARTICLES_PER_LINE: usize = 12;
+
+/// Z position of the effects over the blocks
+const EFFECT_Z: f32 = 0.3;
+
+pub struct EffectPlugin;
+
+impl Plugin for EffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(spawn_drop_effects.after(place_mino))
+            .add_system(spawn_clear_effects.after(place_mino))
+            .add_system(start_shake.after(place_mino))
+            // The effects freeze with the game while it is paused.
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(collapse_rows.after(place_mino))
+                    .with_system(shake_board.after(start_shake))
+                    .with_system(move_particles)
+                    .with_system(fade_effects),
+            )
+            // The results are shown without the effects of the last mino.
+            .add_system_set(SystemSet::on_exit(AppState::InGame).with_system(finish_effects));
+    }
+}
+
+/// Effect which fades out and disappears
+#[derive(Component)]
+struct Fade {
+    elapsed: f32,
+    duration: f32,
+    /// Initial opacity (0-1)
+    alpha: f32,
+}
+
+impl Fade {
+    fn new(duration: f32, alpha: f32) -> Self {
+        Self {
+            elapsed: 0.,
+            duration,
+            alpha,
+        }
+    }
+}
+
+/// Particle flying with the velocity (Cell per second)
+#[derive(Component)]
+struct Particle(Vec2);
+
+/// Fall of a block above the cleared lines
+///
+/// The block is placed at the new row in the board data at once
+/// and only its drawing falls to it.
+#[derive(Component)]
+pub(crate) struct Collapse {
+    /// Whole distance of the fall (Cell)
+    distance: f32,
+    /// Distance already fallen (Cell)
+    fallen: f32,
+    elapsed: f32,
+}
+
+impl Collapse {
+    pub(crate) fn new(distance: f32) -> Self {
+        Self {
+            distance,
+            fallen: 0.,
+            elapsed: 0.,
+        }
+    }
+
+    /// Adds the fall of another line clear to the remaining fall.
+    pub(crate) fn add(&mut self, distance: f32) {
+        *self = Self::new(self.distance - self.fallen + distance);
+    }
+
+    /// Returns the distance fallen at the elapsed time.
+    fn fallen_at(&self, elapsed: f32) -> f32 {
+        let t = ((elapsed - COLLAPSE_DELAY) / COLLAPSE_TIME).clamp(0., 1.);
+        // Accelerates like falling.
+        self.distance * t * t
+    }
+}
+
+/// Shake of the board
+#[derive(Component)]
+struct Shake {
+    /// Initial amplitude (Cell)
+    strength: f32,
+    elapsed: f32,
+}
+
+/// Spawns a quad of the color with the components as an effect on the board.
+#[allow(clippy::too_many_arguments)]
+fn spawn_quad(
+    c: &mut ChildBuilder,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+    size: Vec2,
+    translation: Vec2,
+    color: Color,
+    components: impl Bundle,
+) {
+    c.spawn((
+        MaterialMesh2dBundle {
+            mesh: meshes
+                .add(Mesh::from(shape::Quad { size, ..default() }))
+                .into(),
+            material: materials.add(ColorMaterial::from(color)),
+            transform: Transform::from_translation(translation.extend(EFFECT_Z)),
+            ..default()
+        },
+        components,
+    ));
+}
+
+/// Spawns the hard drop trail and the lock flash.
+fn spawn_drop_effects(
+    mut cmds: Commands,
+    mut drop_reader: EventReader<HardDropEvent>,
+    board_query: Query<(Entity, &Board)>,
+    config: Res<Config>,
+    theme: Res<Theme>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let (board_entity, board) = board_query.single();
+    let effects = config.effects;
+    for drop in drop_reader.iter() {
+        cmds.entity(board_entity).with_children(|c| {
+            if effects.hard_drop_trail() && 0 < drop.distance {
+                let color = theme.mino_color(drop.kind);
+                for (column, top) in column_tops(&drop.cells) {
+                    // From the row the block fell from to the top of the landed block.
+                    let bottom = board.cell_translation(UVec2::new(column, top));
+                    let length = drop.distance as f32 * CELL_SIZE;
+                    let translation = Vec2::new(bottom.x, bottom.y + CELL_SIZE / 2. + length / 2.);
+                    let size = Vec2::new(CELL_SIZE * 0.8, length);
+                    spawn_quad(
+                        c,
+                        &mut meshes,
+                        &mut materials,
+                        size,
+                        translation,
+                        color,
+                        Fade::new(TRAIL_TIME, 0.35),
+                    );
+                }
+            }
+            if effects.lock_flash {
+                for cell in &drop.cells {
+                    spawn_quad(
+                        c,
+                        &mut meshes,
+                        &mut materials,
+                        Vec2::splat(CELL_SIZE),
+                        board.cell_translation(*cell),
+                        Color::WHITE,
+                        Fade::new(LOCK_FLASH_TIME, 0.6),
+                    );
+                }
+            }
+        });
+    }
+}
+
+/// Spawns the flash and the particles of the cleared lines.
+fn spawn_clear_effects(
+    mut cmds: Commands,
+    mut clear_reader: EventReader<LineClearEvent>,
+    board_query: Query<(Entity, &Board)>,
+    config: Res<Config>,
+    theme: Res<Theme>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    let (board_entity, board) = board_query.single();
+    let effects = config.effects;
+    let mut rng = thread_rng();
+    for clear in clear_reader.iter() {
+        cmds.entity(board_entity).with_children(|c| {
+            for &row in &clear.rows {
+                let center = Vec2::new(0., board.cell_translation(UVec2::new(0, row as u32)).y);
+                if effects.line_clear_flash {
+                    let size = Vec2::new(board.size().x * CELL_SIZE, CELL_SIZE);
+                    spawn_quad(
+                        c,
+                        &mut meshes,
+                        &mut materials,
+                        size,
+                        center,
+                        Color::WHITE,
+                        Fade::new(LINE_FLASH_TIME, 0.8),
+                    );
+                }
+                if effects.particles() {
+                    for _ in 0..PARTICLES_PER_LINE {
+                        let x = rng.gen_range(-0.5..0.5) * board.size().x * CELL_SIZE;
+                        let angle = rng.gen_range(0.0..TAU);
+                        let speed = rng.gen_range(2.0..6.0) * CELL_SIZE;
+                        spawn_quad(
+                            c,
+                            &mut meshes,
+                            &mut materials,
+                            Vec2::splat(CELL_SIZE * 0.2),
+                            center + Vec2::new(x, 0.),
+                            theme.frame,
+                            (
+                                Fade::new(PARTICLE_TIME, 1.),
+                                Particle(Vec2::new(angle.cos(), angle.sin()) * speed),
+                            ),
+                        );
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Returns the top row of the blocks in each column.
+fn column_tops(cells: &[UVec2]) -> Vec<(u32, u32)> {
+    let mut tops: Vec<(u32, u32)> = Vec::new();
+    for cell in cells {
+        match tops.iter_mut().find(|(column, _)| *column == cell.x) {
+            Some((_, top)) => *top = (*top).min(cell.y),
+            None => tops.push((cell.x, cell.y)),
+        }
+    }
+    tops.sort_unstable();
+    tops
+}
+
+/// Shakes the board on big line clears.
+fn start_shake(
+    mut cmds: Commands,
+    mut lock_reader: EventReader<LockEvent>,
+    board_query: Query<(Entity, &Board)>,
+    config: Res<Config>,
+) {
+    let (board_entity, board) = board_query.single();
+    for lock in lock_reader.iter() {
+        let strength = shake_strength(lock.lines, lock.perfect_clear);
+        if config.effects.board_shake() && 0. < strength {
+            cmds.entity(board_entity).insert(Shake {
+                strength: strength * board.size().x,
+                elapsed: 0.,
+            });
+        }
+    }
+}
+
+/// Returns the amplitude of the shake relative to the board width.
+///
+/// Only triples or more and perfect clears shake the board.
+fn shake_strength(lines: usize, perfect_clear: bool) -> f32 {
+    let lines = match lines {
+        0..=2 => 0.,
+        3 => 0.01,
+        _ => 0.02,
+    };
+    if perfect_clear {
+        lines + 0.02
+    } else {
+        lines
+    }
+}
+
+/// Swings the board horizontally while shaking.
+fn shake_board(
+    mut cmds: Commands,
+    time: Res<Time>,
+    layout: Res<Layout>,
+    mut board_query: Query<(Entity, &mut Transform, &mut Shake), With<Board>>,
+) {
+    for (entity, mut tf, mut shake) in board_query.iter_mut() {
+        shake.elapsed += time.delta_seconds();
+        let t = shake.elapsed / SHAKE_TIME;
+        if 1. <= t {
+            tf.translation.x = layout.board_center.x;
+            cmds.entity(entity).remove::<Shake>();
+        } else {
+            let decay = (1. - t) * (1. - t);
+            let swing = shake.strength * decay * (shake.elapsed * SHAKE_FREQUENCY * TAU).sin();
+            tf.translation.x = layout.board_center.x + swing * layout.cell_size;
+        }
+    }
+}
+
+/// Moves the blocks above the cleared lines down to their rows.
+fn collapse_rows(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut block_query: Query<(Entity, &mut Transform, &mut Collapse)>,
+) {
+    for (entity, mut tf, mut collapse) in block_query.iter_mut() {
+        collapse.elapsed += time.delta_seconds();
+        let fallen = collapse.fallen_at(collapse.elapsed);
+        tf.translation.y -= fallen - collapse.fallen;
+        collapse.fallen = fallen;
+        if collapse.distance <= fallen {
+            cmds.entity(entity).remove::<Collapse>();
+        }
+    }
+}
+
+/// Moves the particles with the gravity.
+fn move_particles(time: Res<Time>, mut particle_query: Query<(&mut Transform, &mut Particle)>) {
+    let gravity = CELL_SIZE * 20.;
+    let dt = time.delta_seconds();
+    for (mut tf, mut particle) in particle_query.iter_mut() {
+        particle.0.y -= gravity * dt;
+        tf.translation += (particle.0 * dt).extend(0.);
+    }
+}
+
+/// Fades out the effects and despawns the finished ones.
+fn fade_effects(
+    mut cmds: Commands,
+    time: Res<Time>,
+    mut effect_query: Query<(Entity, &Handle<ColorMaterial>, &mut Fade)>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+) {
+    for (entity, material, mut fade) in effect_query.iter_mut() {
+        fade.elapsed += time.delta_seconds();
+        if fade.duration <= fade.elapsed {
+            cmds.entity(entity).despawn_recursive();
+        } else if let Some(material) = materials.get_mut(material) {
+            let alpha = fade.alpha * (1. - fade.elapsed / fade.duration);
+            material.color.set_a(alpha);
+        }
+    }
+}
+
+/// Ends all the effects at once, leaving the board and the blocks where they should be.
+fn finish_effects(
+    mut cmds: Commands,
+    layout: Res<Layout>,
+    fade_query: Query<Entity, With<Fade>>,
+    mut board_query: Query<(Entity, &mut Transform), With<Shake>>,
+    mut block_query: Query<(Entity, &mut Transform, &Collapse), Without<Shake>>,
+) {
+    for entity in fade_query.iter() {
+        cmds.entity(entity).despawn_recursive();
+    }
+    for (entity, mut tf) in board_query.iter_mut() {
+        tf.translation.x = layout.board_center.x;
+        cmds.entity(entity).remove::<Shake>();
+    }
+    for (entity, mut tf, collapse) in block_query.iter_mut() {
+        tf.translation.y -= collapse.distance - collapse.fallen;
+        cmds.entity(entity).remove::<Collapse>();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn collapse_keeps_remaining_fall() {
+        let mut collapse = Collapse::new(30.);
+        assert_eq!(collapse.fallen_at(COLLAPSE_DELAY), 0.);
+        assert_eq!(collapse.fallen_at(COLLAPSE_DELAY + COLLAPSE_TIME), 30.);
+
+        collapse.fallen = 10.;
+        collapse.add(20.);
+        assert_eq!(collapse.distance, 40.);
+        assert_eq!(collapse.fallen, 0.);
+    }
+
+    #[test]
+    fn trail_starts_from_top_of_each_column() {
+        // T mino pointing up at the columns 3..6
+        let cells = [
+            UVec2::new(4, 38),
+            UVec2::new(3, 39),
+            UVec2::new(4, 39),
+            UVec2::new(5, 39),
+        ];
+        assert_eq!(column_tops(&cells), [(3, 39), (4, 38), (5, 39)]);
+    }
+
+    #[test]
+    fn only_big_clears_shake() {
+        assert_eq!(shake_strength(2, false), 0.);
+        assert!(0. < shake_strength(3, false));
+        assert!(shake_strength(3, false) < shake_strength(4, false));
+        assert!(shake_strength(4, false) < shake_strength(4, true));
+        assert!(0. < shake_strength(1, true));
+    }
+}
@@ -0,0 +1,100 @@
+//! Game events of KaGRiS
+
+use crate::{
+    mino::{state::BoardState, MinoType},
+    score::Spin,
+};
+use bevy::prelude::*;
+use kgrs_config::binding::Action;
+
+pub(crate) struct GameEventPlugin;
+
+impl Plugin for GameEventPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<MoveEvent>()
+            .add_event::<LockEvent>()
+            .add_event::<HardDropEvent>()
+            .add_event::<LineClearEvent>()
+            .add_event::<PerfectClearEvent>()
+            .add_event::<StackHeightEvent>()
+            .add_event::<SpawnEvent>()
+            .add_event::<ResetGameEvent>()
+            .add_event::<LoadBoardEvent>()
+            .add_event::<GameOverEvent>();
+    }
+}
+
+/// Sent when the controlled mino is shifted horizontally.
+pub struct MoveEvent;
+
+/// Sent when the controlled mino is placed on the board.
+pub struct LockEvent {
+    /// Number of cells the mino was hard dropped.
+    pub drop_distance: usize,
+    /// Number of cells the mino was soft dropped.
+    pub soft_drop_distance: usize,
+    /// T-spin performed by the mino.
+    pub spin: Spin,
+    /// Number of lines cleared by the mino.
+    pub lines: usize,
+    /// Height of the stack after the lines were cleared.
+    pub stack_height: usize,
+    /// Whether the board became empty by the line clear.
+    pub perfect_clear: bool,
+}
+
+/// Sent when the controlled mino is hard dropped, before the lines are cleared.
+pub struct HardDropEvent {
+    /// Kind of the dropped mino.
+    pub(crate) kind: MinoType,
+    /// Positions of the blocks after the drop (Column, Row).
+    pub cells: Vec<UVec2>,
+    /// Number of cells the mino fell.
+    pub distance: usize,
+}
+
+/// Sent when lines are cleared.
+pub struct LineClearEvent {
+    /// Rows cleared, counted before the clear, in ascending order.
+    pub rows: Vec<usize>,
+}
+
+/// Sent when a line clear leaves the board completely empty.
+pub struct PerfectClearEvent {
+    /// Number of lines cleared by the perfect clear.
+    pub lines: usize,
+}
+
+/// Sent when the stack is changed by a lock or a reset.
+pub struct StackHeightEvent {
+    /// Height of the stack counted from the bottom of the board (Row).
+    pub height: usize,
+}
+
+/// Sent when a new mino is spawned.
+pub struct SpawnEvent {
+    /// Rotation and hold held on spawn to be applied by IRS and IHS.
+    pub initial_actions: Vec<Action>,
+}
+
+/// Requests to clear the board and restart the game.
+pub struct ResetGameEvent {
+    /// Whether to keep the seed and continue from the next bag.
+    /// If `false`, the seed is randomized and a new game is started
+    /// so the score and the stats are also reset.
+    pub keep_seed: bool,
+}
+
+/// Requests to replace the board, the controlled mino, the hold and the queue.
+///
+/// The state must have the same board size as the current board.
+pub struct LoadBoardEvent {
+    /// Board state to replace the current one with.
+    pub state: BoardState,
+}
+
+/// Sent when the game is finished by a top out or by the player.
+pub struct GameOverEvent {
+    /// Whether the game was finished by a top out.
+    pub top_out: bool,
+}
@@ -0,0 +1,309 @@
+//! Action layer combining the keyboard and the gamepads
+//!
+//! Systems read the actions through `Actions` instead of the devices
+//! so every device is handled in the same way.
+//!
+//! The presses and releases in game are also queued in `ActionQueue`
+//! so that the fixed-step simulation doesn't miss a tap pressed and released in one frame.
+//! They are timed at the start of the frame (see "Input timing" in SPECIFICATION.md).
+
+use bevy::{
+    ecs::system::SystemParam,
+    input::{keyboard::KeyboardInput, ButtonState, InputSystem},
+    prelude::*,
+    utils::HashSet,
+};
+use kgrs_config::{binding::Action, Config};
+use kgrs_util::state::AppState;
+use std::{collections::VecDeque, marker::PhantomData};
+
+pub struct ActionPlugin;
+
+impl Plugin for ActionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GamepadActions>()
+            .init_resource::<InputClock>()
+            .init_resource::<ActionQueue>()
+            .add_system_to_stage(CoreStage::PreUpdate, log_gamepads.after(InputSystem))
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                update_gamepad_actions.after(InputSystem),
+            )
+            .add_system_to_stage(
+                CoreStage::PreUpdate,
+                queue_actions.after(update_gamepad_actions),
+            );
+    }
+}
+
+/// Actions pressed on the connected gamepads
+#[derive(Resource, Default)]
+pub struct GamepadActions(pub Input<Action>);
+
+/// Clock counting only the time in game (Second)
+#[derive(Resource, Default)]
+pub struct InputClock {
+    /// Time of the current frame.
+    pub now: f64,
+    /// Time until which the queued actions have been processed.
+    pub processed: f64,
+}
+
+/// Press or release of an action
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ActionEvent {
+    pub action: Action,
+    pub is_pressed: bool,
+    /// Start of the frame the event was received in, on `InputClock`.
+    pub time: f64,
+}
+
+/// Presses and releases of the actions in game waiting to be processed
+#[derive(Resource, Default)]
+pub struct ActionQueue {
+    events: VecDeque<ActionEvent>,
+    /// Actions held as of the last queued event.
+    held: HashSet<Action>,
+}
+
+impl ActionQueue {
+    /// Queues the event if the action was pressed or released.
+    pub fn update(&mut self, action: Action, is_pressed: bool, time: f64) {
+        let was_pressed = self.held.contains(&action);
+        if is_pressed == was_pressed {
+            return;
+        }
+        if is_pressed {
+            self.held.insert(action);
+        } else {
+            self.held.remove(&action);
+        }
+        self.events.push_back(ActionEvent {
+            action,
+            is_pressed,
+            time,
+        });
+    }
+
+    /// Takes the oldest event which happened before `time`.
+    pub fn pop_before(&mut self, time: f64) -> Option<ActionEvent> {
+        match self.events.front() {
+            Some(event) if event.time < time => self.events.pop_front(),
+            _ => None,
+        }
+    }
+}
+
+/// Actions pressed on the keyboard or the gamepads
+#[derive(SystemParam)]
+pub struct Actions<'w, 's> {
+    keys: Res<'w, Input<KeyCode>>,
+    gamepad: Res<'w, GamepadActions>,
+    config: Res<'w, Config>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> Actions<'w, 's> {
+    /// Whether the action is being pressed on any device.
+    pub fn pressed(&self, action: Action) -> bool {
+        self.config.key_bindings.pressed(action, &self.keys) || self.gamepad.0.pressed(action)
+    }
+
+    /// Whether the action was pressed on any device in this frame.
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.config.key_bindings.just_pressed(action, &self.keys)
+            || self.gamepad.0.just_pressed(action)
+    }
+}
+
+/// Actions pressed on the keyboard or the gamepads which can be consumed
+///
+/// It borrows the devices mutably, so only the systems consuming the actions should use it.
+#[derive(SystemParam)]
+pub struct ActionsMut<'w, 's> {
+    keys: ResMut<'w, Input<KeyCode>>,
+    gamepad: ResMut<'w, GamepadActions>,
+    config: Res<'w, Config>,
+    #[system_param(ignore)]
+    marker: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> ActionsMut<'w, 's> {
+    /// Whether the action was pressed on any device in this frame.
+    /// The inputs are consumed so they don't affect the other systems.
+    pub fn clear_just_pressed(&mut self, action: Action) -> bool {
+        let key = self
+            .config
+            .key_bindings
+            .clear_just_pressed(action, &mut self.keys);
+        self.gamepad.0.clear_just_pressed(action) | key
+    }
+}
+
+/// Logs the gamepads connected or disconnected while running.
+fn log_gamepads(mut gamepad_reader: EventReader<GamepadEvent>) {
+    for event in gamepad_reader.iter() {
+        match &event.event_type {
+            GamepadEventType::Connected(info) => {
+                info!("Gamepad {} connected: {}", event.gamepad.id, info.name);
+            }
+            GamepadEventType::Disconnected => {
+                info!("Gamepad {} disconnected", event.gamepad.id);
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Reads the buttons and the left sticks of the connected gamepads as the actions.
+///
+/// The actions of a disconnected gamepad are released.
+fn update_gamepad_actions(
+    gamepads: Res<Gamepads>,
+    buttons: Res<Input<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    config: Res<Config>,
+    mut actions: ResMut<GamepadActions>,
+) {
+    let deadzone = config.stick_deadzone as f32 / 100.;
+    let mut pressed = Vec::new();
+    for gamepad in gamepads.iter() {
+        for action in Action::ALL {
+            let is_pressed = config
+                .button_bindings
+                .inputs(action)
+                .iter()
+                .any(|button| buttons.pressed(GamepadButton::new(gamepad, *button)));
+            if is_pressed {
+                pressed.push(action);
+            }
+        }
+        let axis = |axis_type| {
+            axes.get(GamepadAxis::new(gamepad, axis_type))
+                .unwrap_or_default()
+        };
+        pressed.extend(stick_actions(
+            axis(GamepadAxisType::LeftStickX),
+            axis(GamepadAxisType::LeftStickY),
+            deadzone,
+        ));
+    }
+
+    let actions = &mut actions.0;
+    actions.clear();
+    for action in Action::ALL {
+        let is_pressed = pressed.contains(&action);
+        if is_pressed && !actions.pressed(action) {
+            actions.press(action);
+        } else if !is_pressed && actions.pressed(action) {
+            actions.release(action);
+        }
+    }
+}
+
+/// Queues the presses and releases of the actions in game.
+///
+/// Every key event is queued in order, so a tap released in the same frame isn't lost.
+/// All the events of a frame share its start time.
+#[allow(clippy::too_many_arguments)]
+fn queue_actions(
+    mut key_reader: EventReader<KeyboardInput>,
+    mut held_keys: Local<HashSet<KeyCode>>,
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    config: Res<Config>,
+    gamepad: Res<GamepadActions>,
+    mut clock: ResMut<InputClock>,
+    mut queue: ResMut<ActionQueue>,
+) {
+    let is_in_game = state.current() == &AppState::InGame;
+    let frame_start = clock.now;
+    if is_in_game {
+        clock.now += time.delta_seconds_f64();
+    }
+
+    let is_held = |action, held_keys: &HashSet<KeyCode>| {
+        config
+            .key_bindings
+            .keys(action)
+            .iter()
+            .any(|key| held_keys.contains(key))
+            || gamepad.0.pressed(action)
+    };
+    for event in key_reader.iter() {
+        let key = match event.key_code {
+            Some(key) => key,
+            None => continue,
+        };
+        match event.state {
+            ButtonState::Pressed => held_keys.insert(key),
+            ButtonState::Released => held_keys.remove(&key),
+        };
+        if let (true, Some(action)) = (is_in_game, config.key_bindings.action_of(key)) {
+            queue.update(action, is_held(action, &held_keys), frame_start);
+        }
+    }
+    // Catch up with the gamepads and the changes made out of game.
+    if is_in_game {
+        for action in Action::ALL {
+            queue.update(action, is_held(action, &held_keys), frame_start);
+        }
+    }
+}
+
+/// Returns the movement actions of the stick tilted beyond the deadzone.
+fn stick_actions(x: f32, y: f32, deadzone: f32) -> Vec<Action> {
+    let mut actions = Vec::new();
+    if x < -deadzone {
+        actions.push(Action::MoveLeft);
+    } else if deadzone < x {
+        actions.push(Action::MoveRight);
+    }
+    if y < -deadzone {
+        actions.push(Action::SoftDrop);
+    }
+    actions
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn only_changes_are_queued_in_order() {
+        let mut queue = ActionQueue::default();
+        queue.update(Action::MoveLeft, true, 0.);
+        queue.update(Action::MoveLeft, true, 0.);
+        queue.update(Action::MoveLeft, false, 0.);
+        queue.update(Action::MoveRight, false, 0.1);
+        queue.update(Action::MoveRight, true, 0.1);
+
+        let pressed = |event: ActionEvent| (event.action, event.is_pressed);
+        assert_eq!(
+            queue.pop_before(0.05).map(pressed),
+            Some((Action::MoveLeft, true))
+        );
+        assert_eq!(
+            queue.pop_before(0.05).map(pressed),
+            Some((Action::MoveLeft, false))
+        );
+        assert_eq!(queue.pop_before(0.05), None);
+        assert_eq!(
+            queue.pop_before(0.2).map(pressed),
+            Some((Action::MoveRight, true))
+        );
+    }
+
+    #[test]
+    fn stick_in_deadzone_is_ignored() {
+        assert!(stick_actions(0.3, -0.4, 0.5).is_empty());
+        assert_eq!(stick_actions(-0.8, 0.1, 0.5), [Action::MoveLeft]);
+        assert_eq!(
+            stick_actions(0.6, -0.9, 0.5),
+            [Action::MoveRight, Action::SoftDrop]
+        );
+        // Tilting up does nothing.
+        assert!(stick_actions(0., 1., 0.5).is_empty());
+    }
+}
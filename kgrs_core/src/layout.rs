@@ -0,0 +1,225 @@
+//! Layout of the game screen
+//!
+//! The board, the hold, the next queue and the HUD are positioned from the window size
+//! and `Config::ui_scale` in one place.
+//! The board and everything on it are drawn in cells and scaled by `Layout::cell_size`,
+//! so the frame, the grid and the effects keep their proportions at any window size.
+//!
+//! Landscape and ultrawide windows fit the board to the height.
+//! Portrait windows fit the board, the hold and the next queue to the width.
+
+use bevy::prelude::*;
+use kgrs_config::{Config, ConfigPreview};
+use kgrs_const::dimension::*;
+
+pub struct LayoutPlugin;
+
+impl Plugin for LayoutPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Layout>()
+            .init_resource::<ConfigPreview>()
+            .add_startup_system_to_stage(StartupStage::PreStartup, update_layout)
+            .add_system_to_stage(CoreStage::PreUpdate, update_layout);
+    }
+}
+
+/// Positions and sizes of the game screen
+///
+/// The positions are relative to the window center and the Y-axis points up.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub struct Layout {
+    /// Size of the window.
+    pub window: Vec2,
+    /// Size of a cell of the board.
+    pub cell_size: f32,
+    /// Center of the visible area of the board.
+    pub board_center: Vec2,
+    /// Size of the visible area of the board.
+    pub board_size: Vec2,
+    /// Area of the hold on the left of the board.
+    pub hold: Rect,
+    /// Area of the next queue on the right of the board.
+    pub queue: Rect,
+    /// Top-right corner of the stats under the hold.
+    pub stats_anchor: Vec2,
+    /// Bottom-left corner of the seed under the next queue.
+    pub seed_anchor: Vec2,
+    /// Font size of the HUD values.
+    pub hud_font_size: f32,
+    /// Font size of the menus.
+    pub menu_font_size: f32,
+    /// Font size of the logo.
+    pub logo_font_size: f32,
+}
+
+impl Layout {
+    /// Calculates the layout for the window size, the UI scale percentage
+    /// and the board size (Cell).
+    pub fn new(window: Vec2, ui_scale: u8, board: Vec2) -> Self {
+        let scale = ui_scale as f32 / 100.;
+
+        // The next queue is as tall as the board at least.
+        let rows = board.y.max(QUEUE_HEIGHT);
+        let fit_height = window.y * BOARD_HEIGHT_RATIO / rows;
+        // The hold and the next queue with the margins on both sides of the board.
+        let columns = board.x + (SIDE_PANEL_WIDTH + HUD_MARGIN_RATIO * 2.) * 2.;
+        let fit_width = window.x / columns;
+        let cell_size = (fit_height.min(fit_width) * scale)
+            // Keep the board in the window even when scaled up.
+            .min(window.y / (rows + 1.))
+            .min(window.x / (board.x + 1.));
+
+        let board_size = board * cell_size;
+        let frame = cell_size * FRAME_THICKNESS_RATIO;
+        let margin = cell_size * HUD_MARGIN_RATIO;
+
+        // The bottom is at `BOARD_OFFSET_RATIO_Y` of the window height
+        // and a board smaller than the height allows stays centered on the full-height one.
+        // A board lower than the next queue is aligned to its top.
+        let content_height = rows * cell_size;
+        let content_bottom = (-window.y / 2.
+            + window.y * BOARD_OFFSET_RATIO_Y
+            + frame
+            + (fit_height * rows - content_height) / 2.)
+            .max(-window.y / 2. + frame);
+        let board_top = content_bottom + content_height;
+        let board_bottom = board_top - board_size.y;
+        let board_center = Vec2::new(0., board_bottom + board_size.y / 2.);
+
+        let side = board_size.x / 2. + frame + margin;
+        let hold = Rect::new(
+            -side - SIDE_PANEL_WIDTH * cell_size,
+            board_top - HOLD_HEIGHT * cell_size,
+            -side,
+            board_top,
+        );
+        let queue = Rect::new(
+            side,
+            board_top - QUEUE_HEIGHT * cell_size,
+            side + SIDE_PANEL_WIDTH * cell_size,
+            board_top,
+        );
+
+        // The menus follow the shorter side so that they fit portrait windows.
+        let menu_base = window.x.min(window.y) * scale;
+        Self {
+            window,
+            cell_size,
+            board_center,
+            board_size,
+            hold,
+            queue,
+            stats_anchor: Vec2::new(hold.max.x, hold.min.y - margin),
+            seed_anchor: Vec2::new(queue.min.x, board_bottom),
+            hud_font_size: cell_size * HUD_FONT_SIZE_RATIO,
+            menu_font_size: menu_base * MENU_FONT_SIZE_RATIO,
+            logo_font_size: menu_base * LOGO_FONT_SIZE_RATIO,
+        }
+    }
+
+    /// Converts the position to the window coordinates
+    /// whose origin is the top-left corner and the Y-axis points down.
+    pub fn to_window(&self, pos: Vec2) -> Vec2 {
+        Vec2::new(self.window.x / 2. + pos.x, self.window.y / 2. - pos.y)
+    }
+}
+
+/// Recalculates the layout when the window or the UI scale is changed.
+///
+/// The UI scale in the settings screen is previewed before it is applied.
+fn update_layout(
+    windows: Res<Windows>,
+    config: Res<Config>,
+    preview: Res<ConfigPreview>,
+    mut layout: ResMut<Layout>,
+) {
+    if !windows.is_changed() && !config.is_changed() && !preview.is_changed() {
+        return;
+    }
+    let config = preview.or(&config);
+    let window = match windows.get_primary() {
+        Some(window) => window,
+        None => return,
+    };
+    let board = Vec2::new(config.board.width as f32, config.board.height as f32);
+    let new = Layout::new(
+        Vec2::new(window.width(), window.height()),
+        config.ui_scale,
+        board,
+    );
+    if *layout != new {
+        *layout = new;
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const BOARD_SIZE: Vec2 = Vec2::new(10., 20.);
+
+    /// Whether the rectangle is inside the window.
+    fn is_in_window(layout: &Layout, rect: Rect) -> bool {
+        let half = layout.window / 2.;
+        -half.x <= rect.min.x
+            && -half.y <= rect.min.y
+            && rect.max.x <= half.x
+            && rect.max.y <= half.y
+    }
+
+    fn board_rect(layout: &Layout) -> Rect {
+        Rect::from_center_size(layout.board_center, layout.board_size)
+    }
+
+    #[test]
+    fn fit_landscape_ultrawide_and_portrait() {
+        for window in [
+            Vec2::new(1280., 720.),
+            Vec2::new(3440., 1440.),
+            Vec2::new(720., 1280.),
+        ] {
+            let layout = Layout::new(window, 100, BOARD_SIZE);
+            assert!(is_in_window(&layout, board_rect(&layout)), "{}", window);
+            assert!(is_in_window(&layout, layout.hold), "{}", window);
+            assert!(is_in_window(&layout, layout.queue), "{}", window);
+            assert!(layout.hold.max.x < board_rect(&layout).min.x);
+            assert!(board_rect(&layout).max.x < layout.queue.min.x);
+        }
+    }
+
+    #[test]
+    fn fit_boards_of_other_sizes() {
+        let window = Vec2::new(1280., 720.);
+        for board in [
+            Vec2::new(4., 4.),
+            Vec2::new(4., 20.),
+            Vec2::new(12., 24.),
+            Vec2::new(20., 40.),
+        ] {
+            let layout = Layout::new(window, 100, board);
+            assert!(is_in_window(&layout, board_rect(&layout)), "{}", board);
+            assert!(is_in_window(&layout, layout.hold), "{}", board);
+            assert!(is_in_window(&layout, layout.queue), "{}", board);
+        }
+    }
+
+    #[test]
+    fn board_height_follows_window_height_on_landscape() {
+        let layout = Layout::new(Vec2::new(1280., 720.), 100, BOARD_SIZE);
+        assert!((layout.board_size.y - 720. * BOARD_HEIGHT_RATIO).abs() < 0.01);
+        let ultrawide = Layout::new(Vec2::new(3440., 720.), 100, BOARD_SIZE);
+        assert_eq!(layout.cell_size, ultrawide.cell_size);
+    }
+
+    #[test]
+    fn ui_scale_resizes_and_stays_in_window() {
+        let window = Vec2::new(1280., 720.);
+        let normal = Layout::new(window, 100, BOARD_SIZE);
+        let small = Layout::new(window, 50, BOARD_SIZE);
+        let large = Layout::new(window, 150, BOARD_SIZE);
+        assert!(small.cell_size < normal.cell_size);
+        assert!(normal.cell_size < large.cell_size);
+        assert!(small.hud_font_size < normal.hud_font_size);
+        assert!(is_in_window(&large, board_rect(&large)));
+    }
+}
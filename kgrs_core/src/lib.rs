@@ -1,4 +1,12 @@
 //! The game library for KaGRiS
 
 pub mod board;
+pub mod effect;
+pub mod event;
+pub mod input;
+pub mod layout;
 pub mod mino;
+pub mod mode;
+pub mod score;
+pub mod stats;
+pub mod theme;
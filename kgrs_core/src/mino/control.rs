@@ -1,14 +1,30 @@
+use super::movement::Movement;
 use super::*;
-use kgrs_config::Config;
-use kgrs_util::function::fixed_update;
+use crate::{
+    effect::Collapse,
+    event::{
+        HardDropEvent, LineClearEvent, LockEvent, MoveEvent, PerfectClearEvent, ResetGameEvent,
+        StackHeightEvent,
+    },
+    input::{ActionQueue, Actions, ActionsMut, InputClock},
+    score::Spin,
+};
+use kgrs_config::{binding::Action, Config};
+use kgrs_util::state::AppState;
+use std::collections::VecDeque;
 
 pub(crate) struct MinoControlPlugin;
 
 impl Plugin for MinoControlPlugin {
     fn build(&self, app: &mut App) {
         app.add_startup_system(set_mino_ctrl)
-            .add_system(place_mino)
-            .add_system_set(fixed_update(move_mino));
+            .add_system_set(
+                SystemSet::on_update(AppState::InGame)
+                    .with_system(move_mino.before(place_mino))
+                    .with_system(place_mino)
+                    .with_system(restart_game),
+            )
+            .add_system(reset_game);
     }
 }
 
@@ -17,102 +33,189 @@ pub(crate) fn set_mino_ctrl(mut cmds: Commands) {
     cmds.spawn(MinoCtrl::init());
 }
 
-/// Hard drops the mino.
-fn place_mino(mut mino_ctrl_query: Query<&mut MinoCtrl>, input: Res<Input<KeyCode>>) {
-    if input.just_pressed(KeyCode::Space) {
-        mino_ctrl_query.single_mut().is_waiting = true;
+/// Hard drops the mino and clears the filled lines.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn place_mino(
+    mut cmds: Commands,
+    actions: Actions,
+    config: Res<Config>,
+    mut mino_ctrl_query: Query<&mut MinoCtrl>,
+    mut board_query: Query<&mut Board>,
+    mut mino_mesh_query: Query<(Entity, &mut MinoInfo, &mut Transform, Option<&mut Collapse>)>,
+    mut lock_writer: EventWriter<LockEvent>,
+    mut drop_writer: EventWriter<HardDropEvent>,
+    mut clear_writer: EventWriter<LineClearEvent>,
+    mut pc_writer: EventWriter<PerfectClearEvent>,
+    mut height_writer: EventWriter<StackHeightEvent>,
+) {
+    let mut mino_ctrl = mino_ctrl_query.single_mut();
+    let is_pressed = actions.just_pressed(Action::HardDrop);
+    if !is_pressed || mino_ctrl.is_waiting {
+        return;
+    }
+
+    let mut board = board_query.single_mut();
+
+    let drop_distance = board.data.hard_drop();
+    let mut dropped = Vec::new();
+    for (_, mut mesh, mut tf, _) in mino_mesh_query.iter_mut() {
+        if mesh.is_controlled {
+            mesh.position.y += drop_distance as u32;
+            mesh.is_controlled = false;
+            tf.translation.y -= drop_distance as f32 * CELL_SIZE;
+            dropped.push((mesh.kind, mesh.position));
+        }
+    }
+    if let Some(&(kind, _)) = dropped.first() {
+        drop_writer.send(HardDropEvent {
+            kind,
+            cells: dropped.iter().map(|(_, cell)| *cell).collect(),
+            distance: drop_distance,
+        });
+    }
+
+    let cleared = board.data.clear_lines();
+    if !cleared.is_empty() {
+        for (entity, mut mesh, mut tf, collapse) in mino_mesh_query.iter_mut() {
+            let row = mesh.position.y as usize;
+            if cleared.contains(&row) {
+                cmds.entity(entity).despawn_recursive();
+                continue;
+            }
+            // Drop by the number of cleared rows below.
+            let shift = cleared.iter().filter(|&&r| row < r).count();
+            if shift == 0 {
+                continue;
+            }
+            mesh.position.y += shift as u32;
+            // The drawing may fall after the board data.
+            let distance = shift as f32 * CELL_SIZE;
+            match collapse {
+                Some(mut collapse) => collapse.add(distance),
+                None if config.effects.collapse() => {
+                    cmds.entity(entity).insert(Collapse::new(distance));
+                }
+                None => tf.translation.y -= distance,
+            }
+        }
+        clear_writer.send(LineClearEvent {
+            rows: cleared.clone(),
+        });
     }
+
+    let lines = cleared.len();
+    let perfect_clear = 0 < lines && board.data.is_empty();
+    if perfect_clear {
+        info!("Perfect clear! ({} lines)", lines);
+        pc_writer.send(PerfectClearEvent { lines });
+    }
+    let stack_height = board.data.stack_height();
+    lock_writer.send(LockEvent {
+        drop_distance,
+        // The soft drop and the T-spins are not implemented yet.
+        soft_drop_distance: 0,
+        spin: Spin::No,
+        lines,
+        stack_height,
+        perfect_clear,
+    });
+    height_writer.send(StackHeightEvent {
+        height: stack_height,
+    });
+
+    mino_ctrl.is_waiting = true;
+    mino_ctrl.movement.cut(&config.handling);
 }
 
-/// Moves the mino horizontally.
-fn move_mino(
-    input: Res<Input<KeyCode>>,
+/// Starts a new game with the restart key.
+fn restart_game(mut actions: ActionsMut, mut reset_writer: EventWriter<ResetGameEvent>) {
+    if actions.clear_just_pressed(Action::Restart) {
+        info!("Restarting");
+        reset_writer.send(ResetGameEvent { keep_seed: false });
+    }
+}
+
+/// Clears the board and restarts the game when `ResetGameEvent` is sent.
+fn reset_game(
+    mut cmds: Commands,
+    mut reset_reader: EventReader<ResetGameEvent>,
     mut mino_ctrl_query: Query<&mut MinoCtrl>,
     mut board_query: Query<&mut Board>,
-    mut mino_mesh_query: Query<(&mut MinoInfo, &mut Transform)>,
+    mino_mesh_query: Query<Entity, With<MinoInfo>>,
+    mut height_writer: EventWriter<StackHeightEvent>,
 ) {
-    let keys_iter = input.get_pressed();
-    let mut mino_ctrl = mino_ctrl_query.single_mut();
+    // Multiple requests in the same frame are handled as one.
+    let keep_seed = match reset_reader.iter().last() {
+        Some(reset) => reset.keep_seed,
+        None => return,
+    };
 
-    if keys_iter.len() == 0 {
-        mino_ctrl.movement = Movement::No;
+    for entity in mino_mesh_query.iter() {
+        cmds.entity(entity).despawn_recursive();
     }
+    board_query.single_mut().data.clear();
+    height_writer.send(StackHeightEvent { height: 0 });
 
-    for key in keys_iter {
-        if key == &KeyCode::Left || key == &KeyCode::Right {
-            let input_direction = if let KeyCode::Left = key {
-                MoveDirection::Left
-            } else {
-                MoveDirection::Right
-            };
-            let is_dir_changed = match mino_ctrl.movement {
-                Movement::No => false,
-                Movement::InDas(_, direction) => {
-                    direction != input_direction
-                }
-                Movement::InArr(_, direction) => {
-                    direction != input_direction
-                }
-            };
+    let mut mino_ctrl = mino_ctrl_query.single_mut();
+    if keep_seed {
+        mino_ctrl.align_to_next_bag();
+    } else {
+        // Keep the held directions to move the next mino.
+        let mut movement = std::mem::take(&mut mino_ctrl.movement);
+        movement.stop();
+        *mino_ctrl = MinoCtrl::init();
+        mino_ctrl.movement = movement;
+    }
+    info!("Game reset");
+}
 
-            let cell_size = board_query.single().width / 10.;
-            let board_data = &mut board_query.single_mut().data;
-
-            // Initialize the movement
-            if mino_ctrl.movement == Movement::No || is_dir_changed {
-                board_data.move_mino(
-                    input_direction,
-                    &mut mino_mesh_query,
-                    cell_size,
-                );
-                mino_ctrl.movement = Movement::InDas(0, input_direction);  
+/// Moves the mino horizontally.
+///
+/// The queued inputs are processed in fixed steps of the input rate,
+/// each at the step it happened in.
+fn move_mino(
+    mut queue: ResMut<ActionQueue>,
+    mut clock: ResMut<InputClock>,
+    config: Res<Config>,
+    mut mino_ctrl_query: Query<&mut MinoCtrl>,
+    mut board_query: Query<&mut Board>,
+    mut mino_mesh_query: Query<(&mut MinoInfo, &mut Transform)>,
+    mut move_writer: EventWriter<MoveEvent>,
+) {
+    let mut mino_ctrl = mino_ctrl_query.single_mut();
+    let board_data = &mut board_query.single_mut().data;
+    let mut shift = |direction: Option<MoveDirection>, cells: u32| {
+        if let Some(direction) = direction {
+            let moved = board_data.move_mino(direction, cells, &mut mino_mesh_query, CELL_SIZE);
+            if 0 < moved {
+                move_writer.send(MoveEvent);
             }
+        }
+    };
 
-            match mino_ctrl.movement {
-                Movement::InDas(f, direction) => {
-                    let das = Config::load().handling.das.get();
-                    if f < das {
-                        mino_ctrl.movement.increase();
-                    } else {
-                        board_data.move_mino(
-                            direction,
-                            &mut mino_mesh_query,
-                            cell_size,
-                        );
-                        
-                        mino_ctrl.movement = Movement::InArr(0, direction);
-                    }
-                }
-                Movement::InArr(f, direction) => {
-                    let arr = Config::load().handling.arr;
-                    if arr == 0 {
-                        for _ in 0..10 {
-                            board_data.move_mino(
-                                direction,
-                                &mut mino_mesh_query,
-                                cell_size,
-                            );
-                        }
-                    } else if f < arr {
-                        mino_ctrl.movement.increase();
-                    } else {
-                        board_data.move_mino(
-                            direction,
-                            &mut mino_mesh_query,
-                            cell_size,
-                        );
-                        
-                        mino_ctrl.movement = Movement::InArr(0, direction);
-                    }
-                }
-                Movement::No => {}
+    let step = 1. / config.handling.input_rate as f64;
+    while clock.processed + step <= clock.now {
+        clock.processed += step;
+        while let Some(event) = queue.pop_before(clock.processed) {
+            let direction = match event.action {
+                Action::MoveLeft => MoveDirection::Left,
+                Action::MoveRight => MoveDirection::Right,
+                _ => continue,
+            };
+            if mino_ctrl
+                .movement
+                .set_held(direction, event.is_pressed, &config.handling)
+            {
+                shift(mino_ctrl.movement.direction(), 1);
             }
         }
+        let cells = mino_ctrl.movement.advance(step, &config.handling);
+        shift(mino_ctrl.movement.direction(), cells);
     }
 }
 
 #[derive(Component)]
-pub(crate) struct MinoCtrl {
+pub struct MinoCtrl {
     /// nth of the mino (0-indexed)
     pub(crate) nth: usize,
     /// Seed for RNG.
@@ -121,6 +224,12 @@ pub(crate) struct MinoCtrl {
     pub(crate) is_waiting: bool,
     /// Horizontal movement of the controlled mino.
     pub(crate) movement: Movement,
+    /// Minoes to spawn before the randomized ones, set by a loaded board state.
+    pub(crate) preset: VecDeque<MinoType>,
+    /// Held mino
+    ///
+    /// Only set by a loaded board state until the hold is implemented.
+    pub(crate) hold: Option<MinoType>,
 }
 
 impl MinoCtrl {
@@ -131,35 +240,36 @@ impl MinoCtrl {
             nth: 0,
             seed,
             is_waiting: true,
-            movement: Movement::No,
+            movement: Movement::default(),
+            preset: VecDeque::new(),
+            hold: None,
         }
     }
-}
 
-/// The horizontal movement of the controlled mino.
-#[derive(PartialEq)]
-pub(crate) enum Movement {
-    /// No movement.
-    No,
-    /// In DAS movement.
-    /// contains the timer of DAS and the direction of movement.
-    InDas(u8, MoveDirection),
-    /// In ARR movement.
-    /// contains the timer of each ARR.
-    InArr(u8, MoveDirection),
-}
+    /// Returns the next `len` minoes from the mino to spawn next.
+    pub(crate) fn queue(&self, len: usize) -> Vec<MinoType> {
+        self.preset
+            .iter()
+            .copied()
+            .chain((self.nth..).map(|nth| rand_mino(nth, self.seed)))
+            .take(len)
+            .collect()
+    }
 
-impl Movement {
-    /// Increases the timer rate of the movement.
-    fn increase(&mut self) {
-        match self {
-            Movement::InDas(f, _) => {
-                *f += 1;
-            }
-            Movement::InArr(f, _) => {
-                *f += 1;
-            }
-            _ => {}
+    /// Takes the next mino from the queue.
+    pub(crate) fn advance(&mut self) {
+        if self.preset.pop_front().is_none() {
+            self.nth += 1;
         }
     }
+
+    /// Makes the next mino the first mino of the next bag.
+    /// Does nothing if the next mino is already the first of a bag.
+    fn align_to_next_bag(&mut self) {
+        self.nth = self.nth.div_ceil(7) * 7;
+        self.preset.clear();
+        self.hold = None;
+        self.is_waiting = true;
+        self.movement.stop();
+    }
 }
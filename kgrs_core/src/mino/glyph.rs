@@ -0,0 +1,195 @@
+//! Glyphs drawn over the blocks to tell the kinds of minoes apart without the colors
+//!
+//! Each kind has its own shape so that minoes with similar colors,
+//! such as Z and S or L and O, can still be distinguished.
+//! The glyphs are spawned as children of every block, even if hidden,
+//! so they can be toggled with `Config::mino_glyphs` at any time.
+//!
+//! The glyphs are dark on light blocks and light on dark ones,
+//! so they stay readable with the colors of any theme or palette.
+
+use super::MinoType;
+use bevy::{prelude::*, sprite::MaterialMesh2dBundle};
+use kgrs_config::{Config, ConfigPreview};
+use std::f32::consts::FRAC_PI_4;
+
+/// Color of the glyphs drawn over the light block colors
+const GLYPH_DARK_COL: Color = Color::rgba(0., 0., 0., 0.55);
+
+/// Color of the glyphs drawn over the dark block colors
+const GLYPH_LIGHT_COL: Color = Color::rgba(1., 1., 1., 0.7);
+
+/// Relative luminance above which a block is drawn with the dark glyph
+///
+/// Black and white have the same contrast ratio against it.
+const LIGHT_BLOCK_LUMINANCE: f32 = 0.179;
+
+/// Thickness of the strokes relative to the block size
+const STROKE: f32 = 0.14;
+
+pub(crate) struct GlyphPlugin;
+
+impl Plugin for GlyphPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ConfigPreview>()
+            .add_system(apply_glyph_visibility);
+    }
+}
+
+/// A part of the glyph of a block
+#[derive(Component)]
+pub(crate) struct Glyph;
+
+/// Shape of a part of a glyph
+///
+/// The sizes and the offsets are relative to the block size.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Stroke {
+    /// Rectangle rotated by the angle (Radian).
+    Bar(Vec2, Vec2, f32),
+    /// Circle with the radius.
+    Dot(f32),
+    /// Regular polygon with the radius and the number of sides.
+    Polygon(f32, usize),
+}
+
+impl MinoType {
+    /// Returns the strokes of the glyph of the mino.
+    fn glyph(&self) -> Vec<Stroke> {
+        use Stroke::*;
+        let long = Vec2::new(0.54, STROKE);
+        let tall = Vec2::new(STROKE, 0.54);
+        match self {
+            Self::I => vec![Bar(Vec2::ZERO, Vec2::new(0.6, STROKE), 0.)],
+            Self::O => vec![Dot(0.22)],
+            Self::L => vec![
+                Bar(Vec2::new(-0.2, 0.), tall, 0.),
+                Bar(Vec2::new(0., -0.2), long, 0.),
+            ],
+            Self::J => vec![
+                Bar(Vec2::new(0.2, 0.), tall, 0.),
+                Bar(Vec2::new(0., -0.2), long, 0.),
+            ],
+            Self::Z => vec![Polygon(0.3, 3)],
+            Self::S => vec![Polygon(0.3, 4)],
+            Self::T => vec![Bar(Vec2::new(0., 0.2), long, 0.), Bar(Vec2::ZERO, tall, 0.)],
+            Self::Garbage => vec![
+                Bar(Vec2::ZERO, Vec2::new(0.6, STROKE), FRAC_PI_4),
+                Bar(Vec2::ZERO, Vec2::new(0.6, STROKE), -FRAC_PI_4),
+            ],
+        }
+    }
+}
+
+/// Returns the color of the glyph which contrasts with the block color.
+pub(crate) fn glyph_color(block_color: Color) -> Color {
+    let [r, g, b, _] = block_color.as_linear_rgba_f32();
+    let luminance = 0.2126 * r + 0.7152 * g + 0.0722 * b;
+    if LIGHT_BLOCK_LUMINANCE < luminance {
+        GLYPH_DARK_COL
+    } else {
+        GLYPH_LIGHT_COL
+    }
+}
+
+/// Spawns the glyph of the mino over a block of `block_size` and `block_color`.
+///
+/// `builder` must be the children of the block.
+pub(crate) fn spawn_glyph(
+    builder: &mut ChildBuilder,
+    kind: MinoType,
+    block_size: f32,
+    block_color: Color,
+    is_visible: bool,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    let material = materials.add(ColorMaterial::from(glyph_color(block_color)));
+    for stroke in kind.glyph() {
+        let (mesh, offset, angle) = match stroke {
+            Stroke::Bar(offset, size, angle) => (
+                Mesh::from(shape::Quad {
+                    size: size * block_size,
+                    ..default()
+                }),
+                offset,
+                angle,
+            ),
+            Stroke::Dot(radius) => (
+                Mesh::from(shape::Circle::new(radius * block_size)),
+                Vec2::ZERO,
+                0.,
+            ),
+            Stroke::Polygon(radius, sides) => (
+                Mesh::from(shape::RegularPolygon::new(radius * block_size, sides)),
+                Vec2::ZERO,
+                0.,
+            ),
+        };
+        let offset = offset * block_size;
+        builder.spawn((
+            MaterialMesh2dBundle {
+                mesh: meshes.add(mesh).into(),
+                material: material.clone(),
+                transform: Transform::from_xyz(offset.x, offset.y, 0.01)
+                    .with_rotation(Quat::from_rotation_z(angle)),
+                visibility: Visibility { is_visible },
+                ..default()
+            },
+            Glyph,
+        ));
+    }
+}
+
+/// Shows or hides the glyphs when the config is changed.
+///
+/// The glyphs in the settings screen are previewed before they are applied.
+fn apply_glyph_visibility(
+    config: Res<Config>,
+    preview: Res<ConfigPreview>,
+    mut glyph_query: Query<&mut Visibility, With<Glyph>>,
+) {
+    if !config.is_changed() && !preview.is_changed() {
+        return;
+    }
+    let config = preview.or(&config);
+    for mut visibility in glyph_query.iter_mut() {
+        if visibility.is_visible != config.mino_glyphs {
+            visibility.is_visible = config.mino_glyphs;
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn glyphs_are_distinct() {
+        let kinds = [
+            MinoType::I,
+            MinoType::O,
+            MinoType::L,
+            MinoType::J,
+            MinoType::Z,
+            MinoType::S,
+            MinoType::T,
+            MinoType::Garbage,
+        ];
+        for (i, a) in kinds.iter().enumerate() {
+            for b in &kinds[i + 1..] {
+                assert_ne!(a.glyph(), b.glyph(), "{:?} and {:?}", a, b);
+            }
+        }
+    }
+
+    #[test]
+    fn glyph_contrasts_with_block() {
+        for dark in ["0072b2", "1b3b6f", "6a0dad", "0040ff"] {
+            assert_eq!(glyph_color(Color::hex(dark).unwrap()), GLYPH_LIGHT_COL);
+        }
+        for light in ["f0e442", "56b4e9", "ffd1dc", "ffffff"] {
+            assert_eq!(glyph_color(Color::hex(light).unwrap()), GLYPH_DARK_COL);
+        }
+    }
+}
@@ -1,9 +1,18 @@
-use crate::board::Board;
+use crate::{
+    board::{Board, CELL_SIZE},
+    event::{GameEventPlugin, GameOverEvent, SpawnEvent},
+    input::Actions,
+    theme::Theme,
+};
 use bevy::{ecs::schedule::ShouldRun, prelude::*, sprite::MaterialMesh2dBundle};
 use control::*;
-use kgrs_const::color::mino_color;
+use glyph::*;
+use kgrs_config::{binding::Action, BoardSize, Config, Handling};
+use kgrs_util::state::AppState;
 use mesh::MinoInfo;
 use rand::{thread_rng, Rng};
+use serde::{Deserialize, Serialize};
+use state::BoardStatePlugin;
 use util::*;
 use IsMino::*;
 
@@ -11,17 +20,24 @@ pub struct MinoPlugin;
 
 impl Plugin for MinoPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugin(MinoControlPlugin).add_system_set(
-            SystemSet::new()
-                .with_run_criteria(is_waiting_mino)
-                .with_system(spawn_mino),
-        );
+        app.add_plugin(GameEventPlugin)
+            .add_plugin(MinoControlPlugin)
+            .add_plugin(GlyphPlugin)
+            .add_plugin(BoardStatePlugin)
+            .add_system_set(
+                SystemSet::new()
+                    .with_run_criteria(is_waiting_mino)
+                    .with_system(spawn_mino),
+            );
     }
 }
 
-/// Returns `ShouldRun` based on `MinoCtrl::is_waiting`.
-fn is_waiting_mino(mut mino_ctrl_query: Query<&MinoCtrl>) -> ShouldRun {
-    if mino_ctrl_query.single_mut().is_waiting {
+/// Returns `ShouldRun` based on `MinoCtrl::is_waiting` while in game.
+fn is_waiting_mino(
+    mut mino_ctrl_query: Query<&MinoCtrl>,
+    state: Res<State<AppState>>,
+) -> ShouldRun {
+    if state.current() == &AppState::InGame && mino_ctrl_query.single_mut().is_waiting {
         ShouldRun::Yes
     } else {
         ShouldRun::No
@@ -29,27 +45,44 @@ fn is_waiting_mino(mut mino_ctrl_query: Query<&MinoCtrl>) -> ShouldRun {
 }
 
 /// Spawns a new controlled mino.
+#[allow(clippy::too_many_arguments)]
 fn spawn_mino(
     mut mino_ctrl_query: Query<&mut MinoCtrl>,
     mut cmds: Commands,
     mut board_query: Query<(Entity, &mut Board)>,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<ColorMaterial>>,
+    actions: Actions,
+    config: Res<Config>,
+    theme: Res<Theme>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+    mut spawn_writer: EventWriter<SpawnEvent>,
 ) {
     let mut mino_ctrl = mino_ctrl_query.single_mut();
-    let mino_kind = rand_mino(mino_ctrl.nth, mino_ctrl.seed);
+    let mino_kind = mino_ctrl.queue(1)[0];
 
     let (board_entity, mut board_component) = board_query.single_mut();
+    let spawn_origin = board_component.data.spawn_origin();
+    let (spawn_origin_in_board_x, spawn_origin_in_board_y) =
+        (spawn_origin.x as usize, spawn_origin.y as usize);
 
-    let board_width = board_component.width;
-    let board_height = board_component.height;
-    let one_cell = board_width / 10.;
-    let half_cell = one_cell / 2.;
-    let spawn_origin = Vec2::new(
-        -one_cell - half_cell,
-        board_height / 2. + one_cell * 3. - half_cell,
-    );
-    let (spawn_origin_in_board_x, spawn_origin_in_board_y) = (3, 17);
+    // Top out if the spawn position is blocked.
+    for x in 0..4 {
+        for y in 0..2 {
+            if let M = mino_kind.shape()[y][x] {
+                if board_component.data.rows[spawn_origin_in_board_y + y]
+                    [spawn_origin_in_board_x + x]
+                    .is_filled()
+                {
+                    info!("Topped out");
+                    // Stop spawning until the game is reset.
+                    mino_ctrl.is_waiting = false;
+                    game_over_writer.send(GameOverEvent { top_out: true });
+                    return;
+                }
+            }
+        }
+    }
 
     for x in 0..4 {
         for y in 0..2 {
@@ -62,26 +95,15 @@ fn spawn_mino(
                     board_component
                         .data
                         .spawn_mino(CellState::Controlled, pos_in_board);
-                    c.spawn(MaterialMesh2dBundle {
-                        mesh: meshes
-                            .add(Mesh::from(shape::Quad {
-                                size: Vec2::new(one_cell, one_cell),
-                                ..default()
-                            }))
-                            .into(),
-                        material: materials.add(ColorMaterial::from(mino_kind.color())),
-                        transform: Transform::from_translation(Vec3::new(
-                            spawn_origin.x + x as f32 * one_cell,
-                            spawn_origin.y - y as f32 * one_cell,
-                            0.15,
-                        )),
-                        ..default()
-                    })
-                    .insert(MinoInfo::new(
-                        mino_kind,
-                        pos_in_board,
-                        UVec2::new(x as u32, y as u32),
-                    ));
+                    spawn_block(
+                        c,
+                        &board_component,
+                        MinoInfo::new(mino_kind, pos_in_board),
+                        config.mino_glyphs,
+                        &theme,
+                        &mut meshes,
+                        &mut materials,
+                    );
                 });
             }
         }
@@ -89,12 +111,56 @@ fn spawn_mino(
 
     // println!("Current board:\n{}", board_component.data); // DEBUG
 
-    mino_ctrl.nth += 1;
+    mino_ctrl.advance();
     mino_ctrl.is_waiting = false;
+    mino_ctrl.movement.on_spawn(&config.handling);
+    spawn_writer.send(SpawnEvent {
+        initial_actions: initial_actions(&config.handling, |action| actions.pressed(action)),
+    });
+}
+
+/// Spawns a block of a mino on the board.
+fn spawn_block(
+    board_builder: &mut ChildBuilder,
+    board: &Board,
+    info: MinoInfo,
+    glyphs: bool,
+    theme: &Theme,
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<ColorMaterial>,
+) {
+    let kind = info.kind;
+    board_builder
+        .spawn(MaterialMesh2dBundle {
+            mesh: meshes.add(theme.block_mesh(kind, CELL_SIZE)).into(),
+            material: materials.add(theme.block_material(kind)),
+            transform: Transform::from_translation(
+                board.cell_translation(info.position).extend(0.15),
+            ),
+            ..default()
+        })
+        .insert(info)
+        .with_children(|b| {
+            let color = theme.mino_color(kind);
+            spawn_glyph(b, kind, CELL_SIZE, color, glyphs, meshes, materials)
+        });
+}
+
+/// Returns the rotation and the hold to apply to a new mino by IRS and IHS.
+fn initial_actions(handling: &Handling, is_held: impl Fn(Action) -> bool) -> Vec<Action> {
+    let mut actions = Vec::new();
+    if handling.ihs && is_held(Action::Hold) {
+        actions.push(Action::Hold);
+    }
+    if handling.irs {
+        let rotations = [Action::RotateCw, Action::RotateCcw, Action::Rotate180];
+        actions.extend(rotations.into_iter().find(|rotation| is_held(*rotation)));
+    }
+    actions
 }
 
 /// The kind of mino.
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
 #[rustfmt::skip]
 pub(crate) enum MinoType {
     I, O, L, J, Z, S, T,
@@ -102,6 +168,38 @@ pub(crate) enum MinoType {
 }
 
 impl MinoType {
+    /// The minoes in a bag
+    pub(crate) const BAG: [Self; 7] = [
+        Self::I,
+        Self::O,
+        Self::L,
+        Self::J,
+        Self::Z,
+        Self::S,
+        Self::T,
+    ];
+
+    /// Returns the letter of the mino.
+    pub(crate) fn letter(&self) -> char {
+        match self {
+            Self::I => 'I',
+            Self::O => 'O',
+            Self::L => 'L',
+            Self::J => 'J',
+            Self::Z => 'Z',
+            Self::S => 'S',
+            Self::T => 'T',
+            Self::Garbage => 'G',
+        }
+    }
+
+    /// Returns the mino in a bag of the letter.
+    pub(crate) fn from_letter(letter: char) -> Option<Self> {
+        Self::BAG
+            .into_iter()
+            .find(|kind| kind.letter() == letter.to_ascii_uppercase())
+    }
+
     /// Returns the 4x2 shape of the mino.
     ///
     /// # Panics
@@ -143,60 +241,6 @@ impl MinoType {
             }
         }
     }
-
-    /// Returns relative axis point of the mino.
-    ///
-    /// # Panics
-    ///
-    /// If `MinoType::Garbage` was passed.
-    fn axis_point(&self) -> Vec2 {
-        match self {
-            Self::I => Vec2::new(2.0, 2.0),
-            Self::O => Vec2::new(2.0, 1.0),
-            Self::L => Vec2::new(1.5, 1.5),
-            Self::J => Vec2::new(1.5, 1.5),
-            Self::Z => Vec2::new(1.5, 1.5),
-            Self::S => Vec2::new(1.5, 1.5),
-            Self::T => Vec2::new(1.5, 1.5),
-            Self::Garbage => {
-                unreachable!()
-            }
-        }
-    }
-
-    /// Return a color of the mino.
-    fn color(&self) -> Color {
-        match self {
-            Self::I => mino_color::I,
-            Self::O => mino_color::O,
-            Self::L => mino_color::L,
-            Self::J => mino_color::J,
-            Self::Z => mino_color::Z,
-            Self::S => mino_color::S,
-            Self::T => mino_color::T,
-            Self::Garbage => mino_color::GARBAGE,
-        }
-    }
-
-    /// Returns side margin of the mino in 4x2 shape.
-    ///
-    /// # Panics
-    ///
-    /// If `MinoType::Garbage` was passed.
-    fn side_margin(&self) -> (isize, isize) {
-        match self {
-            Self::I => (0, 0),
-            Self::O => (1, 1),
-            Self::L => (0, 1),
-            Self::J => (0, 1),
-            Self::Z => (0, 1),
-            Self::S => (0, 1),
-            Self::T => (0, 1),
-            Self::Garbage => {
-                unreachable!()
-            }
-        }
-    }
 }
 
 /// Whether a block is a part of the mino.
@@ -208,81 +252,220 @@ enum IsMino {
 }
 
 /// Mino data for the board
-pub(crate) struct MinoData([[CellState; 10]; 40]);
+///
+/// Can be written as text and parsed from it (see `state`).
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct MinoData {
+    /// Rows of the cells from the top of the buffer.
+    rows: Vec<Vec<CellState>>,
+    /// Number of the hidden rows above the visible area.
+    buffer: usize,
+}
 
 impl MinoData {
-    /// Creates a empty mino data.
-    pub(crate) fn empty() -> Self {
-        Self([[CellState::Empty; 10]; 40])
+    /// Creates a empty mino data of the size.
+    pub(crate) fn new(size: BoardSize) -> Self {
+        let rows = size.buffer as usize + size.height as usize;
+        Self {
+            rows: vec![vec![CellState::Empty; size.width as usize]; rows],
+            buffer: size.buffer as usize,
+        }
+    }
+
+    /// Returns the size of the board.
+    pub(crate) fn size(&self) -> BoardSize {
+        BoardSize {
+            width: self.width() as u8,
+            height: (self.rows.len() - self.buffer) as u8,
+            buffer: self.buffer as u8,
+        }
+    }
+
+    /// Returns the number of the columns.
+    pub(crate) fn width(&self) -> usize {
+        self.rows[0].len()
+    }
+
+    /// Returns the number of the hidden rows above the visible area.
+    pub(crate) fn buffer(&self) -> usize {
+        self.buffer
+    }
+
+    /// Returns the top-left cell of the 4x2 area where the minoes spawn.
+    ///
+    /// The area is centered horizontally (leaning to the left)
+    /// and its bottom row is the second row above the visible area.
+    pub(crate) fn spawn_origin(&self) -> UVec2 {
+        UVec2::new(
+            (self.width().saturating_sub(4) / 2) as u32,
+            (self.buffer - 3) as u32,
+        )
+    }
+
+    /// Empties all cells.
+    pub(crate) fn clear(&mut self) {
+        for cell in self.rows.iter_mut().flatten() {
+            *cell = CellState::Empty;
+        }
+    }
+
+    /// Whether no cell of the board is filled.
+    pub(crate) fn is_empty(&self) -> bool {
+        self.rows.iter().flatten().all(|cell| cell.is_empty())
+    }
+
+    /// Returns the height of the stack counted from the bottom of the board.
+    pub(crate) fn stack_height(&self) -> usize {
+        match self
+            .rows
+            .iter()
+            .position(|row| row.iter().any(|cell| cell.is_filled()))
+        {
+            Some(top) => self.rows.len() - top,
+            None => 0,
+        }
     }
 
     /// Spawns a mino to specified position.
     pub(crate) fn spawn_mino(&mut self, state: CellState, pos: UVec2) {
-        self.0[pos.y as usize][pos.x as usize] = state;
+        self.rows[pos.y as usize][pos.x as usize] = state;
     }
 
-    /// Moves the controlled mino.
+    /// Moves the controlled mino by up to `cells` columns.
+    /// The mino stops in front of the walls and the placed minoes.
+    ///
+    /// Returns the number of columns the mino was moved.
     pub(crate) fn move_mino(
         &mut self,
         direction: MoveDirection,
+        cells: u32,
         mino_mesh_query: &mut Query<(&mut MinoInfo, &mut Transform)>,
         cell_size: f32,
-    ) {
-        for r in 0..self.0.len() {
-            let row = &mut self.0[r];
-            let row_len = row.len();
-            for mut c in 0..row_len {
-                // Reverse the index if the direction is right.
-                // Because if move in order from the left minoes,
-                // the minoes will be overlapped by the right minoes.
-                c = if direction.is_left() {
-                    c
+    ) -> usize {
+        let distance = self.shift_distance(direction).min(cells as usize);
+        if distance == 0 {
+            return 0;
+        }
+        for row in self.rows.iter_mut() {
+            let controlled: Vec<_> = row
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| cell.is_controlled())
+                .map(|(c, _)| c)
+                .collect();
+            for &c in &controlled {
+                row[c] = CellState::Empty;
+            }
+            for &c in &controlled {
+                let moved = if direction.is_left() {
+                    c - distance
                 } else {
-                    row.len() - c - 1
+                    c + distance
                 };
-                if row[c].is_controlled() {
-                    for (mut mesh, mut tf) in mino_mesh_query.iter_mut() {
-                        if mesh.position == UVec2::new(c as u32, r as u32) {
-                            let part_pos = mesh.part.x as isize;
-                            let side_margin = mesh.kind.side_margin();
-                            let is_not_mino_on_edge = if direction.is_left() {
-                                !(c as isize - part_pos + side_margin.0 == 0)
-                            } else {
-                                !(c as isize + (3 - part_pos) - side_margin.1
-                                    == row_len as isize - 1)
-                            };
-
-                            if is_not_mino_on_edge {
-                                // Remove the mino to move.
-                                row[c] = CellState::Empty;
-
-                                // The cell that mino moves to turns into controlled mino.
-                                let moved_pos = if direction.is_left() { c - 1 } else { c + 1 };
-                                row[moved_pos] = CellState::Controlled;
-
-                                // Update rendering position.
-                                mesh.position = UVec2::new(moved_pos as u32, r as u32);
-                                tf.translation.x += if direction.is_left() {
-                                    -cell_size
-                                } else {
-                                    cell_size
-                                };
-                            }
-                        }
+                row[moved] = CellState::Controlled;
+            }
+        }
+
+        // Update rendering position.
+        for (mut mesh, mut tf) in mino_mesh_query.iter_mut() {
+            if mesh.is_controlled {
+                if direction.is_left() {
+                    mesh.position.x -= distance as u32;
+                    tf.translation.x -= distance as f32 * cell_size;
+                } else {
+                    mesh.position.x += distance as u32;
+                    tf.translation.x += distance as f32 * cell_size;
+                }
+            }
+        }
+        distance
+    }
+
+    /// Returns how many columns the controlled mino can move to the direction.
+    fn shift_distance(&self, direction: MoveDirection) -> usize {
+        let mut distance = None;
+        for row in self.rows.iter() {
+            for (c, cell) in row.iter().enumerate() {
+                if !cell.is_controlled() {
+                    continue;
+                }
+                let mut d = 0;
+                loop {
+                    let next = if direction.is_left() {
+                        c.checked_sub(d + 1)
+                    } else {
+                        Some(c + d + 1).filter(|&next| next < row.len())
+                    };
+                    match next {
+                        Some(next) if !row[next].is_placed() => d += 1,
+                        _ => break,
+                    }
+                }
+                distance = Some(distance.map_or(d, |min: usize| min.min(d)));
+            }
+        }
+        distance.unwrap_or(0)
+    }
+
+    /// Drops the controlled mino as far as possible and places it.
+    ///
+    /// Returns the number of cells the mino was dropped.
+    pub(crate) fn hard_drop(&mut self) -> usize {
+        let distance = self.drop_distance();
+        // Move from the bottom rows so that the unmoved cells are not overwritten.
+        for r in (0..self.rows.len()).rev() {
+            for c in 0..self.rows[r].len() {
+                if self.rows[r][c].is_controlled() {
+                    self.rows[r][c] = CellState::Empty;
+                    self.rows[r + distance][c] = CellState::Placed;
+                }
+            }
+        }
+        distance
+    }
+
+    /// Returns how many cells the controlled mino can fall.
+    fn drop_distance(&self) -> usize {
+        let height = self.rows.len();
+        let mut distance = None;
+        for r in 0..height {
+            for c in 0..self.rows[r].len() {
+                if self.rows[r][c].is_controlled() {
+                    let mut d = 0;
+                    while r + d + 1 < height && !self.rows[r + d + 1][c].is_placed() {
+                        d += 1;
                     }
+                    distance = Some(distance.map_or(d, |min: usize| min.min(d)));
                 }
             }
         }
+        distance.unwrap_or(0)
+    }
+
+    /// Clears all filled rows and drops the rows above them.
+    ///
+    /// Returns the indices of the cleared rows in ascending order.
+    pub(crate) fn clear_lines(&mut self) -> Vec<usize> {
+        let cleared = (0..self.rows.len())
+            .filter(|&r| self.rows[r].iter().all(|cell| cell.is_placed()))
+            .collect::<Vec<_>>();
+        // Rows below the cleared row are not shifted,
+        // so the remaining indices are still valid in ascending order.
+        for &r in &cleared {
+            self.rows.remove(r);
+            self.rows.insert(0, vec![CellState::Empty; self.width()]);
+        }
+        cleared
     }
 }
 
 impl std::fmt::Display for MinoData {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        for (r, row) in self.0.iter().enumerate() {
+        for (r, row) in self.rows.iter().enumerate() {
             for (c, cell) in row.iter().enumerate() {
                 let frame = |at: usize| -> String {
                     String::from(if c == at {
-                        if 19 < r {
+                        if self.buffer <= r {
                             "!"
                         } else {
                             " "
@@ -297,13 +480,13 @@ impl std::fmt::Display for MinoData {
                     CellState::Controlled => write!(f, "{{}}")?,
                     CellState::Placed => write!(f, "[]")?,
                 }
-                write!(f, "{}", frame(9))?;
+                write!(f, "{}", frame(self.width() - 1))?;
             }
             writeln!(f)?;
-            if r == 19 {
-                writeln!(f, "!--------------------!")?;
-            } else if r == 39 {
-                writeln!(f, "======================")?;
+            if r + 1 == self.buffer {
+                writeln!(f, "!{}!", "--".repeat(self.width()))?;
+            } else if r + 1 == self.rows.len() {
+                writeln!(f, "{}", "=".repeat(self.width() * 2 + 2))?;
             }
         }
         Ok(())
@@ -311,7 +494,7 @@ impl std::fmt::Display for MinoData {
 }
 
 /// State of the mino cell in the board
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub(crate) enum CellState {
     /// Empty
     Empty,
@@ -344,5 +527,131 @@ impl CellState {
 }
 
 pub(crate) mod control;
+pub(crate) mod glyph;
 pub(crate) mod mesh;
+pub(crate) mod movement;
+pub mod state;
 pub(crate) mod util;
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Fills the bottom `rows` rows except the column `hole`.
+    fn board_with_hole(rows: usize, hole: usize) -> MinoData {
+        let mut data = MinoData::new(BoardSize::default());
+        for r in 40 - rows..40 {
+            for c in 0..10 {
+                if c != hole {
+                    data.rows[r][c] = CellState::Placed;
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn hard_drop_stops_on_placed_minoes() {
+        let mut data = board_with_hole(2, 0);
+        // Vertical I mino above the hole
+        for r in 17..21 {
+            data.spawn_mino(CellState::Controlled, UVec2::new(0, r));
+        }
+        // 17..21 -> 36..40
+        assert_eq!(data.hard_drop(), 19);
+        assert!(data.rows[39][0].is_placed());
+        assert!(data.rows[36][0].is_placed());
+        assert!(data.rows[35][0].is_empty());
+    }
+
+    #[test]
+    fn clear_lines_drops_rows_above() {
+        let mut data = board_with_hole(3, 4);
+        data.rows[36][4] = CellState::Placed;
+        data.rows[38][4] = CellState::Placed;
+
+        assert_eq!(data.clear_lines(), vec![38]);
+        assert_eq!(data.stack_height(), 3);
+        // The remaining rows keep their order.
+        assert!(data.rows[38][4].is_empty());
+        assert!(data.rows[39][4].is_empty());
+        assert!(data.rows[37][4].is_placed());
+    }
+
+    #[test]
+    fn initial_actions_follow_irs_and_ihs() {
+        let held = |action| matches!(action, Action::Hold | Action::RotateCcw);
+        assert!(initial_actions(&Handling::default(), held).is_empty());
+
+        let handling = Handling {
+            irs: true,
+            ihs: true,
+            ..default()
+        };
+        assert_eq!(
+            initial_actions(&handling, held),
+            [Action::Hold, Action::RotateCcw]
+        );
+        assert!(initial_actions(&handling, |_| false).is_empty());
+    }
+
+    #[test]
+    fn shift_stops_at_walls_and_placed_minoes() {
+        let mut data = MinoData::new(BoardSize::default());
+        // T mino at the columns 3..6
+        data.spawn_mino(CellState::Controlled, UVec2::new(4, 20));
+        for c in 3..6 {
+            data.spawn_mino(CellState::Controlled, UVec2::new(c, 21));
+        }
+        assert_eq!(data.shift_distance(MoveDirection::Left), 3);
+        assert_eq!(data.shift_distance(MoveDirection::Right), 4);
+
+        data.rows[20][1] = CellState::Placed;
+        data.rows[21][8] = CellState::Placed;
+        assert_eq!(data.shift_distance(MoveDirection::Left), 2);
+        assert_eq!(data.shift_distance(MoveDirection::Right), 2);
+    }
+
+    #[test]
+    fn perfect_clear_empties_the_board() {
+        let mut data = board_with_hole(4, 9);
+        for r in 10..14 {
+            data.spawn_mino(CellState::Controlled, UVec2::new(9, r));
+        }
+        data.hard_drop();
+
+        assert_eq!(data.clear_lines(), vec![36, 37, 38, 39]);
+        assert!(data.is_empty());
+        assert_eq!(data.stack_height(), 0);
+    }
+
+    #[test]
+    fn boards_of_other_sizes() {
+        let size = BoardSize {
+            width: 4,
+            height: 6,
+            buffer: 4,
+        };
+        let mut data = MinoData::new(size);
+        assert_eq!(data.size(), size);
+        assert_eq!(data.spawn_origin(), UVec2::new(0, 1));
+
+        // I mino dropped onto the bottom of the 4-wide board
+        for c in 0..4 {
+            data.spawn_mino(CellState::Controlled, UVec2::new(c, 2));
+        }
+        assert_eq!(data.hard_drop(), 7);
+        assert_eq!(data.clear_lines(), vec![9]);
+        assert!(data.is_empty());
+
+        let big = MinoData::new(BoardSize {
+            width: 12,
+            height: 24,
+            buffer: 20,
+        });
+        assert_eq!(big.spawn_origin(), UVec2::new(4, 17));
+        let text = big.to_string();
+        assert_eq!(text.lines().count(), 44 + 2);
+        assert!(text.contains(&format!("!{}!", "-".repeat(24))));
+    }
+}
@@ -0,0 +1,344 @@
+//! Horizontal movement of the controlled mino
+//!
+//! The movement is a state machine driven by the presses and releases of the
+//! direction actions and by the elapsed time, so it doesn't depend on the frame rate.
+
+use super::util::MoveDirection;
+use kgrs_config::{BothHeld, Handling};
+
+/// Length of a frame in which DAS and ARR are counted (Second)
+pub(crate) const FRAME: f64 = 1. / 60.;
+
+/// Number of cells to shift the mino to the furthest column with ARR 0
+pub(crate) const TO_WALL: u32 = u32::MAX;
+
+/// Tolerance of the rounding error of the accumulated time
+const EPSILON: f64 = 1e-9;
+
+/// The horizontal movement of the controlled mino.
+#[derive(Default)]
+pub(crate) struct Movement {
+    is_left_held: bool,
+    is_right_held: bool,
+    /// Direction pressed most recently.
+    last_pressed: Option<MoveDirection>,
+    phase: Phase,
+}
+
+/// Phase of the movement
+#[derive(Clone, Copy, Debug, PartialEq, Default)]
+pub(crate) enum Phase {
+    /// No movement.
+    #[default]
+    No,
+    /// In DAS movement.
+    /// contains the charged time and the direction of movement.
+    InDas(f64, MoveDirection),
+    /// In ARR movement.
+    /// contains the time since the last shift.
+    InArr(f64, MoveDirection),
+}
+
+impl Movement {
+    /// Returns the current phase.
+    #[cfg(test)]
+    pub(crate) fn phase(&self) -> Phase {
+        self.phase
+    }
+
+    /// Returns the direction of the movement.
+    pub(crate) fn direction(&self) -> Option<MoveDirection> {
+        match self.phase {
+            Phase::No => None,
+            Phase::InDas(_, direction) | Phase::InArr(_, direction) => Some(direction),
+        }
+    }
+
+    /// Stops the movement keeping the held directions.
+    /// It starts again on the next `advance` if a direction is still held.
+    pub(crate) fn stop(&mut self) {
+        self.phase = Phase::No;
+    }
+
+    /// Applies DAS carry or DAS cut to the held movement when a new mino spawns.
+    pub(crate) fn on_spawn(&mut self, handling: &Handling) {
+        match self.phase {
+            Phase::InDas(_, direction) | Phase::InArr(_, direction) if !handling.das_carry => {
+                self.phase = Phase::InDas(0., direction);
+            }
+            _ => self.cut(handling),
+        }
+    }
+
+    /// Suspends the charged auto shift for the DAS cut delay.
+    pub(crate) fn cut(&mut self, handling: &Handling) {
+        if let Phase::InArr(_, direction) = self.phase {
+            self.phase = Phase::InArr(-(handling.dcd as f64 * FRAME), direction);
+        }
+    }
+
+    /// Presses or releases the direction.
+    ///
+    /// Returns whether the mino should be shifted once immediately.
+    pub(crate) fn set_held(
+        &mut self,
+        direction: MoveDirection,
+        is_held: bool,
+        handling: &Handling,
+    ) -> bool {
+        match direction {
+            MoveDirection::Left => self.is_left_held = is_held,
+            MoveDirection::Right => self.is_right_held = is_held,
+        }
+        if is_held {
+            self.last_pressed = Some(direction);
+        }
+        match self.held_direction(handling.both_held) {
+            Some(direction) => self.start(direction),
+            None => {
+                self.phase = Phase::No;
+                false
+            }
+        }
+    }
+
+    /// Returns the direction to move to by the held directions.
+    fn held_direction(&self, both_held: BothHeld) -> Option<MoveDirection> {
+        match (self.is_left_held, self.is_right_held) {
+            (true, false) => Some(MoveDirection::Left),
+            (false, true) => Some(MoveDirection::Right),
+            (true, true) => match both_held {
+                BothHeld::KeepCurrent => self.direction().or(self.last_pressed),
+                BothHeld::PreferLast => self.last_pressed,
+                BothHeld::Cancel => None,
+            },
+            (false, false) => None,
+        }
+    }
+
+    /// Starts moving to the direction unless already moving to it.
+    fn start(&mut self, direction: MoveDirection) -> bool {
+        if self.direction() == Some(direction) {
+            return false;
+        }
+        self.phase = Phase::InDas(0., direction);
+        true
+    }
+
+    /// Advances the time by `dt` seconds.
+    ///
+    /// Returns the number of cells to shift the mino to `direction()`,
+    /// or `TO_WALL` to shift it as far as possible.
+    pub(crate) fn advance(&mut self, dt: f64, handling: &Handling) -> u32 {
+        let mut shifts: u32 = 0;
+        if self.phase == Phase::No {
+            match self.held_direction(handling.both_held) {
+                Some(direction) if self.start(direction) => shifts += 1,
+                _ => return 0,
+            }
+        }
+
+        let das = handling.das.get() as f64 * FRAME;
+        let arr = handling.arr as f64 * FRAME;
+        match self.phase {
+            Phase::InDas(t, direction) => {
+                let t = t + dt;
+                if t + EPSILON < das {
+                    self.phase = Phase::InDas(t, direction);
+                } else {
+                    // The remaining time is carried to ARR.
+                    self.phase = Phase::InArr(t - das, direction);
+                    shifts = (shifts + 1).saturating_add(self.repeat(arr));
+                }
+            }
+            Phase::InArr(t, direction) => {
+                self.phase = Phase::InArr(t + dt, direction);
+                shifts = shifts.saturating_add(self.repeat(arr));
+            }
+            Phase::No => {}
+        }
+        shifts
+    }
+
+    /// Consumes the time of ARR and returns the number of repeated shifts.
+    fn repeat(&mut self, arr: f64) -> u32 {
+        if let Phase::InArr(t, direction) = self.phase {
+            if t + EPSILON < 0. {
+                // Still in the DAS cut delay.
+                return 0;
+            }
+            if arr <= 0. {
+                self.phase = Phase::InArr(0., direction);
+                return TO_WALL;
+            }
+            let shifts = ((t + EPSILON) / arr).floor();
+            self.phase = Phase::InArr((t - shifts * arr).max(0.), direction);
+            return shifts as u32;
+        }
+        0
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use std::num::NonZeroU8;
+
+    fn handling(das: u8, arr: f32) -> Handling {
+        Handling {
+            das: NonZeroU8::new(das).unwrap(),
+            arr,
+            ..Default::default()
+        }
+    }
+
+    /// Advances the movement frame by frame and returns the total shifts.
+    fn run(movement: &mut Movement, frames: u32, handling: &Handling) -> u32 {
+        (0..frames).fold(0, |shifts: u32, _| {
+            shifts.saturating_add(movement.advance(FRAME, handling))
+        })
+    }
+
+    #[test]
+    fn tap_shorter_than_a_frame_shifts_once() {
+        let handling = handling(11, 2.);
+        let mut movement = Movement::default();
+        assert!(movement.set_held(MoveDirection::Left, true, &handling));
+        assert!(!movement.set_held(MoveDirection::Left, false, &handling));
+        assert_eq!(movement.phase(), Phase::No);
+        assert_eq!(run(&mut movement, 30, &handling), 0);
+    }
+
+    #[test]
+    fn das_and_arr_are_counted_in_time() {
+        let handling = handling(10, 2.);
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Right, true, &handling);
+        assert_eq!(run(&mut movement, 9, &handling), 0);
+        // DAS is charged on the 10th frame.
+        assert_eq!(run(&mut movement, 1, &handling), 1);
+        assert_eq!(run(&mut movement, 1, &handling), 0);
+        assert_eq!(run(&mut movement, 1, &handling), 1);
+
+        // Higher input rates give the same result.
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Right, true, &handling);
+        let shifts: u32 = (0..48)
+            .map(|_| movement.advance(FRAME / 4., &handling))
+            .sum();
+        assert_eq!(shifts, 2);
+    }
+
+    #[test]
+    fn both_held_keeps_current_direction() {
+        let handling = handling(10, 2.);
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Left, true, &handling);
+        run(&mut movement, 20, &handling);
+        assert!(!movement.set_held(MoveDirection::Right, true, &handling));
+        assert_eq!(movement.direction(), Some(MoveDirection::Left));
+        // Releasing the left key turns to the right with a new DAS.
+        assert!(movement.set_held(MoveDirection::Left, false, &handling));
+        assert_eq!(movement.phase(), Phase::InDas(0., MoveDirection::Right));
+    }
+
+    #[test]
+    fn last_pressed_direction_is_preferred() {
+        let handling = Handling {
+            both_held: BothHeld::PreferLast,
+            ..handling(10, 2.)
+        };
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Left, true, &handling);
+        run(&mut movement, 20, &handling);
+        assert!(movement.set_held(MoveDirection::Right, true, &handling));
+        assert_eq!(movement.phase(), Phase::InDas(0., MoveDirection::Right));
+        // Releasing the left key doesn't restart the movement.
+        assert!(!movement.set_held(MoveDirection::Left, false, &handling));
+        assert_eq!(movement.direction(), Some(MoveDirection::Right));
+    }
+
+    #[test]
+    fn both_held_cancels_movement() {
+        let handling = Handling {
+            both_held: BothHeld::Cancel,
+            ..handling(10, 2.)
+        };
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Left, true, &handling);
+        assert!(!movement.set_held(MoveDirection::Right, true, &handling));
+        assert_eq!(movement.phase(), Phase::No);
+        assert_eq!(run(&mut movement, 20, &handling), 0);
+        assert!(movement.set_held(MoveDirection::Right, false, &handling));
+        assert_eq!(movement.direction(), Some(MoveDirection::Left));
+    }
+
+    #[test]
+    fn das_carry_across_spawns() {
+        let carry = handling(10, 2.);
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Left, true, &carry);
+        run(&mut movement, 10, &carry);
+        movement.on_spawn(&carry);
+        // The charged DAS repeats on the new mino right away.
+        assert_eq!(run(&mut movement, 2, &carry), 1);
+
+        let no_carry = Handling {
+            das_carry: false,
+            ..carry
+        };
+        movement.on_spawn(&no_carry);
+        assert_eq!(movement.phase(), Phase::InDas(0., MoveDirection::Left));
+        assert_eq!(run(&mut movement, 9, &no_carry), 0);
+        assert_eq!(run(&mut movement, 1, &no_carry), 1);
+    }
+
+    #[test]
+    fn das_cut_delays_auto_shift() {
+        let handling = Handling {
+            dcd: 3,
+            ..handling(10, 0.)
+        };
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Right, true, &handling);
+        run(&mut movement, 10, &handling);
+        movement.cut(&handling);
+        assert_eq!(run(&mut movement, 2, &handling), 0);
+        assert_eq!(run(&mut movement, 1, &handling), TO_WALL);
+
+        // DAS which is not charged yet is not affected.
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Right, true, &handling);
+        run(&mut movement, 5, &handling);
+        movement.cut(&handling);
+        assert!(matches!(
+            movement.phase(),
+            Phase::InDas(t, MoveDirection::Right) if (t - 5. * FRAME).abs() < EPSILON
+        ));
+    }
+
+    #[test]
+    fn fractional_arr() {
+        let half = handling(1, 0.5);
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Left, true, &half);
+        // DAS is charged in the first frame, then 2 repeats per frame.
+        assert_eq!(run(&mut movement, 1, &half), 1);
+        assert_eq!(run(&mut movement, 3, &half), 6);
+
+        let one_and_half = handling(1, 1.5);
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Left, true, &one_and_half);
+        run(&mut movement, 1, &one_and_half);
+        assert_eq!(run(&mut movement, 3, &one_and_half), 2);
+    }
+
+    #[test]
+    fn arr_0_shifts_to_wall() {
+        let handling = handling(2, 0.);
+        let mut movement = Movement::default();
+        movement.set_held(MoveDirection::Right, true, &handling);
+        assert_eq!(run(&mut movement, 1, &handling), 0);
+        assert_eq!(run(&mut movement, 1, &handling), TO_WALL);
+    }
+}
@@ -0,0 +1,626 @@
+//! Board states in text and JSON
+//!
+//! A board state is the board, the controlled mino, the hold and the next queue of a game.
+//! It can be copied out of a game for a bug report and pasted back as a practice setup.
+//!
+//! The text starts with optional `field: value` lines followed by the rows of the board:
+//!
+//! ```text
+//! size: 10x20+20
+//! active: T
+//! hold: I
+//! queue: OSZLJ
+//! ....@.....
+//! ...@@@....
+//! ##.#######
+//! ```
+//!
+//! - `size` is `<width>x<height>+<buffer>`.
+//!   Without it, the width is taken from the rows and the heights from the board:
+//!   the board of the config with `BoardState::parse`, the default one with `FromStr`.
+//! - The rows are aligned to the bottom of the board and the missing rows above are empty.
+//! - `.` is an empty cell, `#` is a placed block and `@` is a block of the controlled mino.
+//!   The board doesn't keep the kinds of the placed blocks, so they are loaded as garbage.
+//!
+//! The JSON has the same fields with the size as an object and the rows as strings.
+
+use super::{
+    control::MinoCtrl, mesh::MinoInfo, spawn_block, CellState, IsMino, MinoData, MinoType,
+};
+use crate::{
+    board::Board,
+    event::{LoadBoardEvent, StackHeightEvent},
+    theme::Theme,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use kgrs_config::{BoardSize, Config};
+use serde::{Deserialize, Serialize};
+use std::{fmt, str::FromStr};
+
+/// Number of the next minoes written to a board state at least
+const QUEUE_LEN: usize = 5;
+
+pub(crate) struct BoardStatePlugin;
+
+impl Plugin for BoardStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(load_board);
+    }
+}
+
+/// Board, controlled mino, hold and next queue of a game
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct BoardState {
+    /// Cells of the board
+    #[serde(with = "board_json")]
+    pub(crate) board: MinoData,
+    /// Kind of the controlled mino whose blocks are on the board.
+    pub(crate) active: Option<MinoType>,
+    /// Held mino
+    pub(crate) hold: Option<MinoType>,
+    /// Next minoes from the one to spawn next
+    #[serde(default)]
+    pub(crate) queue: Vec<MinoType>,
+}
+
+impl BoardState {
+    /// Returns the size of the board.
+    pub fn size(&self) -> BoardSize {
+        self.board.size()
+    }
+
+    /// Parses the JSON if it starts with `{`, or the text otherwise.
+    ///
+    /// A text without `size` is read with the heights of `board`.
+    pub fn parse(s: &str, board: BoardSize) -> Result<Self, BoardStateError> {
+        if s.trim_start().starts_with('{') {
+            return Self::from_json(s);
+        }
+        let state = parse_text(s, board)?;
+        state.check()?;
+        Ok(state)
+    }
+
+    /// Parses the JSON.
+    pub fn from_json(json: &str) -> Result<Self, BoardStateError> {
+        let state: Self = serde_json::from_str(json)
+            .map_err(|why| BoardStateError::Unreadable(why.to_string()))?;
+        state.check()?;
+        Ok(state)
+    }
+
+    /// Returns the state as JSON in a line.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+
+    /// Checks that the controlled blocks on the board are the active mino
+    /// and that every mino can be controlled.
+    fn check(&self) -> Result<(), BoardStateError> {
+        let controlled = filled_cells(&self.board.rows, CellState::is_controlled);
+        let reason = match (self.active, controlled.is_empty()) {
+            _ if self
+                .active
+                .iter()
+                .chain(&self.hold)
+                .chain(&self.queue)
+                .any(|kind| *kind == MinoType::Garbage) =>
+            {
+                "garbage can't be controlled, held or queued"
+            }
+            (Some(_), true) => "the active mino has no blocks on the board",
+            (None, false) => "the blocks of the controlled mino need `active`",
+            (Some(active), false) if !is_shape_of(active, &controlled) => {
+                "the controlled blocks are not the shape of the active mino"
+            }
+            _ => return Ok(()),
+        };
+        Err(BoardStateError::InvalidBoard(String::from(reason)))
+    }
+}
+
+impl fmt::Display for BoardState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "size: {}", format_size(self.size()))?;
+        if let Some(active) = self.active {
+            writeln!(f, "active: {}", active.letter())?;
+        }
+        if let Some(hold) = self.hold {
+            writeln!(f, "hold: {}", hold.letter())?;
+        }
+        if !self.queue.is_empty() {
+            let queue = self.queue.iter().map(MinoType::letter).collect::<String>();
+            writeln!(f, "queue: {}", queue)?;
+        }
+        for row in self.board.text_rows() {
+            writeln!(f, "{}", row)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for BoardState {
+    type Err = BoardStateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse(s, BoardSize::default())
+    }
+}
+
+impl FromStr for MinoData {
+    type Err = BoardStateError;
+
+    /// Parses the text of a board state and ignores the minoes other than the board.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ok(parse_text(s, BoardSize::default())?.board)
+    }
+}
+
+/// Problem found in a board state
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum BoardStateError {
+    /// The JSON couldn't be parsed.
+    Unreadable(String),
+    /// The line of the text is invalid.
+    InvalidLine {
+        /// Line number counted from 1.
+        line: usize,
+        reason: String,
+    },
+    /// The size or the minoes don't fit the board.
+    InvalidBoard(String),
+}
+
+impl fmt::Display for BoardStateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            BoardStateError::Unreadable(why) => write!(f, "Unreadable JSON: {}", why),
+            BoardStateError::InvalidLine { line, reason } => write!(f, "Line {}: {}", line, reason),
+            BoardStateError::InvalidBoard(reason) => write!(f, "Invalid board: {}", reason),
+        }
+    }
+}
+
+impl MinoData {
+    /// Creates the board of the size from the rows of the text aligned to the bottom.
+    ///
+    /// Returns the index of the invalid row and the reason on error.
+    fn from_rows<'a>(
+        size: BoardSize,
+        rows: impl IntoIterator<Item = &'a str>,
+    ) -> Result<Self, (usize, String)> {
+        let mut data = Self::new(size);
+        let rows = rows.into_iter().collect::<Vec<_>>();
+        let top = data.rows.len().checked_sub(rows.len()).ok_or_else(|| {
+            let reason = format!("{} rows exceed the board", rows.len());
+            (0, reason)
+        })?;
+        for (i, row) in rows.into_iter().enumerate() {
+            let cells = row
+                .chars()
+                .map(|cell| match cell {
+                    '.' => Ok(CellState::Empty),
+                    '#' => Ok(CellState::Placed),
+                    '@' => Ok(CellState::Controlled),
+                    _ => Err((i, format!("invalid cell `{}`", cell))),
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            if cells.len() != data.width() {
+                let reason = format!("{} cells instead of {}", cells.len(), data.width());
+                return Err((i, reason));
+            }
+            data.rows[top + i] = cells;
+        }
+        Ok(data)
+    }
+
+    /// Returns the rows of the text from the top of the visible area
+    /// or from the highest block in the buffer.
+    fn text_rows(&self) -> Vec<String> {
+        let top = self
+            .rows
+            .iter()
+            .position(|row| row.iter().any(|cell| cell.is_filled()))
+            .map_or(self.buffer, |top| top.min(self.buffer));
+        self.rows[top..]
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .map(|cell| match cell {
+                        CellState::Empty => '.',
+                        CellState::Placed => '#',
+                        CellState::Controlled => '@',
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// Returns the positions of the cells of the rows which satisfy `is_filled`.
+fn filled_cells<T>(rows: &[impl AsRef<[T]>], is_filled: impl Fn(&T) -> bool) -> Vec<IVec2> {
+    rows.iter()
+        .enumerate()
+        .flat_map(|(y, row)| {
+            row.as_ref()
+                .iter()
+                .enumerate()
+                .filter(|(_, cell)| is_filled(cell))
+                .map(move |(x, _)| IVec2::new(x as i32, y as i32))
+                .collect::<Vec<_>>()
+        })
+        .collect()
+}
+
+/// Whether the blocks are the mino in any orientation.
+fn is_shape_of(kind: MinoType, blocks: &[IVec2]) -> bool {
+    // Moves the blocks to the origin in a fixed order to compare them.
+    let normalize = |blocks: &[IVec2]| {
+        let min = blocks
+            .iter()
+            .fold(IVec2::splat(i32::MAX), |min, b| min.min(*b));
+        let mut blocks = blocks.iter().map(|b| *b - min).collect::<Vec<_>>();
+        blocks.sort_by_key(|b| (b.y, b.x));
+        blocks
+    };
+    let blocks = normalize(blocks);
+    let mut shape = filled_cells(&kind.shape(), |b| matches!(b, IsMino::M));
+    (0..4).any(|_| {
+        shape = shape.iter().map(|b| IVec2::new(b.y, -b.x)).collect();
+        normalize(&shape) == blocks
+    })
+}
+
+/// Parses the text without checking the minoes against the board.
+///
+/// The heights of `board` are used if the text has no size.
+fn parse_text(text: &str, board: BoardSize) -> Result<BoardState, BoardStateError> {
+    let mut size = None;
+    let mut active = None;
+    let mut hold = None;
+    let mut queue = Vec::new();
+    // Line numbers and rows
+    let mut rows = Vec::new();
+    for (i, line) in text.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        // The fields are only before the rows.
+        let (key, value) = match line.split_once(':').filter(|_| rows.is_empty()) {
+            Some((key, value)) => (key.trim(), value.trim()),
+            None => {
+                rows.push((i + 1, line));
+                continue;
+            }
+        };
+        let invalid = |reason: String| BoardStateError::InvalidLine {
+            line: i + 1,
+            reason,
+        };
+        let mino = |letter: char| {
+            MinoType::from_letter(letter)
+                .ok_or_else(|| invalid(format!("invalid mino `{}`", letter)))
+        };
+        let single = |value: &str| {
+            let mut letters = value.chars();
+            match (letters.next(), letters.next()) {
+                (Some(letter), None) => mino(letter),
+                _ => Err(invalid(format!("`{}` is not a mino", value))),
+            }
+        };
+        match key {
+            "size" => {
+                let parsed = parse_size(value);
+                size = Some(parsed.ok_or_else(|| invalid(format!("invalid size `{}`", value)))?);
+            }
+            "active" => active = Some(single(value)?),
+            "hold" => hold = Some(single(value)?),
+            "queue" => {
+                queue = value
+                    .chars()
+                    .filter(|letter| !letter.is_whitespace())
+                    .map(mino)
+                    .collect::<Result<_, _>>()?;
+            }
+            _ => return Err(invalid(format!("unknown field `{}`", key))),
+        }
+    }
+
+    let size = size.unwrap_or_else(|| BoardSize {
+        width: rows.first().map_or(board.width, |(_, row)| {
+            row.chars().count().min(u8::MAX as usize) as u8
+        }),
+        ..board
+    });
+    if !size.is_valid() {
+        let reason = format!("size {} is out of range", format_size(size));
+        return Err(BoardStateError::InvalidBoard(reason));
+    }
+    let board =
+        MinoData::from_rows(size, rows.iter().map(|(_, row)| *row)).map_err(|(i, reason)| {
+            BoardStateError::InvalidLine {
+                line: rows[i].0,
+                reason,
+            }
+        })?;
+    Ok(BoardState {
+        board,
+        active,
+        hold,
+        queue,
+    })
+}
+
+/// Returns the size as `<width>x<height>+<buffer>`.
+fn format_size(size: BoardSize) -> String {
+    format!("{}x{}+{}", size.width, size.height, size.buffer)
+}
+
+/// Parses `<width>x<height>+<buffer>`.
+fn parse_size(s: &str) -> Option<BoardSize> {
+    let (width, heights) = s.split_once('x')?;
+    let (height, buffer) = heights.split_once('+')?;
+    Some(BoardSize {
+        width: width.trim().parse().ok()?,
+        height: height.trim().parse().ok()?,
+        buffer: buffer.trim().parse().ok()?,
+    })
+}
+
+/// Serializes the board as the size and the rows of the text.
+mod board_json {
+    use super::*;
+    use serde::{de::Error, Deserializer, Serializer};
+
+    #[derive(Serialize, Deserialize)]
+    struct BoardJson {
+        size: BoardSize,
+        rows: Vec<String>,
+    }
+
+    pub(super) fn serialize<S: Serializer>(
+        board: &MinoData,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        BoardJson {
+            size: board.size(),
+            rows: board.text_rows(),
+        }
+        .serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<MinoData, D::Error> {
+        let json = BoardJson::deserialize(deserializer)?;
+        if !json.size.is_valid() {
+            let why = format!("size {} is out of range", format_size(json.size));
+            return Err(D::Error::custom(why));
+        }
+        MinoData::from_rows(json.size, json.rows.iter().map(String::as_str))
+            .map_err(|(i, why)| D::Error::custom(format!("row {}: {}", i + 1, why)))
+    }
+}
+
+/// Board state of the current game
+#[derive(SystemParam)]
+pub struct CurrentBoardState<'w, 's> {
+    board_query: Query<'w, 's, &'static Board>,
+    mino_ctrl_query: Query<'w, 's, &'static MinoCtrl>,
+    mino_mesh_query: Query<'w, 's, &'static MinoInfo>,
+}
+
+impl<'w, 's> CurrentBoardState<'w, 's> {
+    /// Returns the board state of the current game.
+    pub fn get(&self) -> BoardState {
+        let mino_ctrl = self.mino_ctrl_query.single();
+        BoardState {
+            board: self.board_query.single().data.clone(),
+            active: self
+                .mino_mesh_query
+                .iter()
+                .find(|info| info.is_controlled)
+                .map(|info| info.kind),
+            hold: mino_ctrl.hold,
+            queue: mino_ctrl.queue(QUEUE_LEN.max(mino_ctrl.preset.len())),
+        }
+    }
+}
+
+/// Replaces the board and the minoes with the state of `LoadBoardEvent`.
+///
+/// The randomizer continues after the loaded queue.
+#[allow(clippy::too_many_arguments)]
+fn load_board(
+    mut cmds: Commands,
+    mut load_reader: EventReader<LoadBoardEvent>,
+    mut mino_ctrl_query: Query<&mut MinoCtrl>,
+    mut board_query: Query<(Entity, &mut Board)>,
+    mino_mesh_query: Query<Entity, With<MinoInfo>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    config: Res<Config>,
+    theme: Res<Theme>,
+    mut height_writer: EventWriter<StackHeightEvent>,
+) {
+    let state = match load_reader.iter().last() {
+        Some(load) => &load.state,
+        None => return,
+    };
+    let (board_entity, mut board) = board_query.single_mut();
+    if state.size() != board.data.size() {
+        warn!(
+            "The board state of {} doesn't fit the board",
+            format_size(state.size())
+        );
+        return;
+    }
+
+    for entity in mino_mesh_query.iter() {
+        cmds.entity(entity).despawn_recursive();
+    }
+    board.data = state.board.clone();
+
+    let cells = board.data.rows.iter().enumerate().flat_map(|(r, row)| {
+        row.iter()
+            .enumerate()
+            .map(move |(c, cell)| (UVec2::new(c as u32, r as u32), *cell))
+    });
+    cmds.entity(board_entity).with_children(|c| {
+        for (pos, cell) in cells {
+            let info = match cell {
+                CellState::Empty => continue,
+                CellState::Controlled => {
+                    MinoInfo::new(state.active.unwrap_or(MinoType::Garbage), pos)
+                }
+                CellState::Placed => MinoInfo {
+                    is_controlled: false,
+                    ..MinoInfo::new(MinoType::Garbage, pos)
+                },
+            };
+            spawn_block(
+                c,
+                &board,
+                info,
+                config.mino_glyphs,
+                &theme,
+                &mut meshes,
+                &mut materials,
+            );
+        }
+    });
+    height_writer.send(StackHeightEvent {
+        height: board.data.stack_height(),
+    });
+
+    let mut mino_ctrl = mino_ctrl_query.single_mut();
+    mino_ctrl.preset = state.queue.iter().copied().collect();
+    mino_ctrl.hold = state.hold;
+    mino_ctrl.is_waiting = state.active.is_none();
+    mino_ctrl.movement.stop();
+    info!("Board state loaded");
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const STATE: &str = "
+        size: 10x20+20
+        active: T
+        hold: I
+        queue: OSZLJ
+        ....@.....
+        ...@@@....
+        ##.#######
+        #.########
+    ";
+
+    #[test]
+    fn text_and_json_round_trip() {
+        let state = STATE.parse::<BoardState>().unwrap();
+        assert_eq!(state.active, Some(MinoType::T));
+        assert_eq!(state.hold, Some(MinoType::I));
+        assert_eq!(state.queue.len(), 5);
+        assert!(state.board.rows[38][2].is_empty());
+        assert!(state.board.rows[39][0].is_placed());
+        assert!(state.board.rows[36][4].is_controlled());
+        // The empty rows above the visible area are omitted.
+        assert_eq!(state.to_string().lines().count(), 4 + 20);
+
+        assert_eq!(state.to_string().parse::<BoardState>().unwrap(), state);
+        assert_eq!(
+            BoardState::parse(&state.to_json(), BoardSize::default()).unwrap(),
+            state
+        );
+    }
+
+    #[test]
+    fn board_from_literal() {
+        let mut data = "
+            ......@...
+            ......@...
+            ......@...
+            ......@...
+            ######.###
+            ######.###
+        "
+        .parse::<MinoData>()
+        .unwrap();
+        assert_eq!(data.size(), BoardSize::default());
+        assert_eq!(data.hard_drop(), 2);
+        assert_eq!(data.clear_lines(), vec![38, 39]);
+        let expected = "
+            ......#...
+            ......#...
+        "
+        .parse::<MinoData>()
+        .unwrap();
+        assert_eq!(data, expected);
+
+        let small = "size: 4x4+4\n#..#".parse::<MinoData>().unwrap();
+        assert_eq!(small.spawn_origin(), UVec2::new(0, 1));
+
+        // The heights are taken from the board without the size.
+        let board = BoardSize {
+            width: 6,
+            height: 8,
+            buffer: 4,
+        };
+        let state = BoardState::parse("#..#\n####", board).unwrap();
+        assert_eq!(state.size(), BoardSize { width: 4, ..board });
+    }
+
+    #[test]
+    fn report_invalid_lines_and_boards() {
+        let line = |text: &str| match text.parse::<BoardState>() {
+            Err(BoardStateError::InvalidLine { line, .. }) => Some(line),
+            _ => None,
+        };
+        assert_eq!(line("size: 10x20\n.........."), Some(1));
+        assert_eq!(line("hold: IO\n.........."), Some(1));
+        assert_eq!(line("queue: IOX"), Some(1));
+        assert_eq!(line("color: red"), Some(1));
+        assert_eq!(line("..........\n.........\n"), Some(2));
+        assert_eq!(line("..........\n....x....."), Some(2));
+        assert_eq!(line(&"....\n".repeat(9)), None);
+        assert_eq!(
+            line(&format!("size: 4x4+4\n{}", "....\n".repeat(9))),
+            Some(2)
+        );
+
+        assert!(matches!(
+            "...".parse::<BoardState>(),
+            Err(BoardStateError::InvalidBoard(_))
+        ));
+        assert!(matches!(
+            "active: T\n..........".parse::<BoardState>(),
+            Err(BoardStateError::InvalidBoard(_))
+        ));
+        assert!(matches!(
+            "....@.....".parse::<BoardState>(),
+            Err(BoardStateError::InvalidBoard(_))
+        ));
+        assert!(matches!(
+            BoardState::parse("{\"board\": 1}", BoardSize::default()),
+            Err(BoardStateError::Unreadable(_))
+        ));
+    }
+
+    #[test]
+    fn controlled_blocks_are_active_mino() {
+        let is_valid = |text: &str| text.parse::<BoardState>().is_ok();
+        // T rotated to the left
+        assert!(is_valid("active: T\n....@.....\n...@@.....\n....@....."));
+        // I standing
+        assert!(is_valid(
+            "active: I\n@.........\n@.........\n@.........\n@........."
+        ));
+        // Too few, too many or the mirrored shape
+        assert!(!is_valid("active: T\n...@@@...."));
+        assert!(!is_valid("active: O\n...@@.....\n...@@@...."));
+        assert!(!is_valid("active: L\n...@......\n...@@@...."));
+        assert!(!is_valid("active: T\n@.......@.\n.@@......."));
+    }
+}
@@ -9,15 +9,7 @@ use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
 /// - `seed`: seed for RNG
 pub(crate) fn rand_mino(nth: usize, seed: u64) -> MinoType {
     let nth_bag = nth / 7;
-    let mut minoes = [
-        MinoType::I,
-        MinoType::O,
-        MinoType::L,
-        MinoType::J,
-        MinoType::Z,
-        MinoType::S,
-        MinoType::T,
-    ];
+    let mut minoes = MinoType::BAG;
     let nth_in_bag = nth % 7;
 
     let mut rng = StdRng::seed_from_u64(seed + nth_bag as u64);
@@ -26,7 +18,7 @@ pub(crate) fn rand_mino(nth: usize, seed: u64) -> MinoType {
 }
 
 /// The movement direction of the mino.
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 pub(crate) enum MoveDirection {
     Left,
     Right,
@@ -37,11 +29,6 @@ impl MoveDirection {
     pub(crate) fn is_left(&self) -> bool {
         matches!(self, Self::Left)
     }
-
-    /// Whether the direction is right.
-    pub(crate) fn is_right(&self) -> bool {
-        matches!(self, Self::Right)
-    }
 }
 
 #[cfg(test)]
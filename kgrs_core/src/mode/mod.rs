@@ -0,0 +1,43 @@
+use crate::event::ResetGameEvent;
+use bevy::prelude::*;
+use pc_practice::PcPracticePlugin;
+use serde::{Deserialize, Serialize};
+
+pub struct ModePlugin;
+
+impl Plugin for ModePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameMode>()
+            .add_plugin(PcPracticePlugin)
+            .add_system(restart_on_mode_change);
+    }
+}
+
+/// The game mode
+#[derive(Resource, Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum GameMode {
+    /// Free play without any goal.
+    #[default]
+    Free,
+    /// Practice of 4-line perfect clears from an empty board.
+    PcPractice,
+}
+
+impl std::fmt::Display for GameMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GameMode::Free => write!(f, "Free Play"),
+            GameMode::PcPractice => write!(f, "PC Practice"),
+        }
+    }
+}
+
+/// Restarts the game when the game mode is changed.
+fn restart_on_mode_change(mode: Res<GameMode>, mut reset_writer: EventWriter<ResetGameEvent>) {
+    if mode.is_changed() && !mode.is_added() {
+        info!("Game mode changed to {}", *mode);
+        reset_writer.send(ResetGameEvent { keep_seed: false });
+    }
+}
+
+pub mod pc_practice;
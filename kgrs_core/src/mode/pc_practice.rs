@@ -0,0 +1,244 @@
+use super::GameMode;
+use crate::event::{LockEvent, ResetGameEvent};
+use bevy::prelude::*;
+use kgrs_config::{
+    profile::{ActiveProfile, PcRecord},
+    Config,
+};
+use std::collections::HashMap;
+
+pub(crate) struct PcPracticePlugin;
+
+impl Plugin for PcPracticePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PcPractice>()
+            .add_system(track_pc_attempt);
+    }
+}
+
+/// Number of lines to clear in a PC attempt.
+const PC_LINES: usize = 4;
+
+/// Returns the number of minoes needed for a 4-line PC on the board of the width.
+fn pc_minoes(width: u8) -> usize {
+    PC_LINES * width as usize / 4
+}
+
+/// State and results of the PC practice mode
+#[derive(Resource)]
+pub struct PcPractice {
+    /// Name of the opener currently practiced.
+    /// Results are recorded for each opener.
+    pub opener: String,
+    /// Number of minoes placed in the current attempt.
+    placed: usize,
+    /// Number of lines cleared in the current attempt.
+    cleared: usize,
+    /// Results of the attempts played as a guest for each opener.
+    /// The results of a profile are only recorded to the profile.
+    guest_records: HashMap<String, PcRecord>,
+}
+
+impl PcPractice {
+    /// Returns the results of each opener
+    /// from the selected profile, or of the guest.
+    pub fn records<'a>(&'a self, profile: &'a ActiveProfile) -> &'a HashMap<String, PcRecord> {
+        match &profile.0 {
+            Some(profile) => &profile.records.pc_practice,
+            None => &self.guest_records,
+        }
+    }
+
+    /// Returns the results of the current opener.
+    pub fn stats(&self, profile: &ActiveProfile) -> PcStats {
+        let record = self.records(profile).get(&self.opener).copied();
+        record.map(PcStats::from).unwrap_or_default()
+    }
+
+    /// Records the result of an attempt to the current opener.
+    fn record(&mut self, profile: &mut ActiveProfile, is_success: bool) {
+        let records = match &mut profile.0 {
+            Some(profile) => &mut profile.records.pc_practice,
+            None => &mut self.guest_records,
+        };
+        let record = records.entry(self.opener.clone()).or_default();
+        record.attempts += 1;
+        if is_success {
+            record.successes += 1;
+        }
+        info!(
+            "PC attempt {} ({}: {}/{})",
+            if is_success { "succeeded" } else { "failed" },
+            self.opener,
+            record.successes,
+            record.attempts
+        );
+        self.restart();
+    }
+
+    /// Clears the progress of the current attempt.
+    fn restart(&mut self) {
+        self.placed = 0;
+        self.cleared = 0;
+    }
+
+    /// Counts the locked mino and returns the result if the attempt is finished.
+    ///
+    /// Only a perfect clear of exactly `PC_LINES` lines succeeds.
+    /// A perfect clear of fewer lines fails as it leaves nothing of the setup.
+    fn judge(&mut self, lock: &LockEvent, width: u8) -> Option<bool> {
+        self.placed += 1;
+        self.cleared += lock.lines;
+        if lock.perfect_clear {
+            Some(self.cleared == PC_LINES)
+        } else if pc_minoes(width) <= self.placed || PC_LINES < lock.stack_height {
+            Some(false)
+        } else {
+            None
+        }
+    }
+}
+
+impl Default for PcPractice {
+    fn default() -> Self {
+        Self {
+            opener: String::from("Free"),
+            placed: 0,
+            cleared: 0,
+            guest_records: HashMap::default(),
+        }
+    }
+}
+
+/// Results of the PC attempts for an opener
+#[derive(Clone, Copy, Default, PartialEq, Eq)]
+pub struct PcStats {
+    /// Number of the finished attempts.
+    pub attempts: u32,
+    /// Number of the attempts ended with a 4-line perfect clear.
+    pub successes: u32,
+}
+
+impl From<PcRecord> for PcStats {
+    fn from(record: PcRecord) -> Self {
+        Self {
+            attempts: record.attempts,
+            successes: record.successes,
+        }
+    }
+}
+
+impl PcStats {
+    /// Returns the success rate (0.0-1.0).
+    /// If no attempt has finished, returns 0.0.
+    pub fn success_rate(&self) -> f32 {
+        if self.attempts == 0 {
+            0.
+        } else {
+            self.successes as f32 / self.attempts as f32
+        }
+    }
+}
+
+/// Judges the current PC attempt and restarts from the next bag when it finishes.
+fn track_pc_attempt(
+    mode: Res<GameMode>,
+    config: Res<Config>,
+    mut practice: ResMut<PcPractice>,
+    mut profile: ResMut<ActiveProfile>,
+    mut lock_reader: EventReader<LockEvent>,
+    mut reset_writer: EventWriter<ResetGameEvent>,
+) {
+    // The game is restarted when the mode is changed.
+    if mode.is_changed() {
+        practice.restart();
+    }
+    if *mode != GameMode::PcPractice {
+        lock_reader.clear();
+        return;
+    }
+
+    for lock in lock_reader.iter() {
+        if let Some(is_success) = practice.judge(lock, config.board.width) {
+            practice.record(&mut profile, is_success);
+            reset_writer.send(ResetGameEvent { keep_seed: true });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::score::Spin;
+
+    #[test]
+    fn success_rate_of_each_opener() {
+        let mut guest = ActiveProfile::default();
+        let mut practice = PcPractice {
+            opener: String::from("PCO"),
+            ..default()
+        };
+        practice.record(&mut guest, true);
+        practice.record(&mut guest, false);
+        practice.record(&mut guest, true);
+        practice.record(&mut guest, true);
+        assert_eq!(practice.stats(&guest).success_rate(), 0.75);
+
+        practice.opener = String::from("DPC");
+        practice.record(&mut guest, false);
+        assert_eq!(practice.stats(&guest).success_rate(), 0.);
+        assert_eq!(PcStats::default().success_rate(), 0.);
+    }
+
+    #[test]
+    fn results_of_profile_are_recorded_to_profile() {
+        let mut profile = ActiveProfile(Some(default()));
+        let mut practice = PcPractice::default();
+        practice.record(&mut profile, true);
+        practice.record(&mut profile, false);
+
+        let record = profile.0.as_ref().unwrap().records.pc_practice["Free"];
+        assert_eq!((record.attempts, record.successes), (2, 1));
+        assert_eq!(practice.stats(&profile).success_rate(), 0.5);
+        assert!(practice.guest_records.is_empty());
+        assert_eq!(practice.stats(&ActiveProfile(None)).attempts, 0);
+    }
+
+    fn lock(lines: usize, stack_height: usize, perfect_clear: bool) -> LockEvent {
+        LockEvent {
+            drop_distance: 0,
+            soft_drop_distance: 0,
+            spin: Spin::No,
+            lines,
+            stack_height,
+            perfect_clear,
+        }
+    }
+
+    #[test]
+    fn only_pc_of_pc_lines_succeeds() {
+        let mut practice = PcPractice::default();
+        for _ in 0..4 {
+            assert_eq!(practice.judge(&lock(0, 2, false), 10), None);
+        }
+        assert_eq!(practice.judge(&lock(2, 2, false), 10), None);
+        assert_eq!(practice.judge(&lock(2, 0, true), 10), Some(true));
+
+        // A 2-line PC ends the attempt before the 4-line PC.
+        practice.restart();
+        for _ in 0..4 {
+            practice.judge(&lock(0, 2, false), 10);
+        }
+        assert_eq!(practice.judge(&lock(2, 0, true), 10), Some(false));
+
+        practice.restart();
+        assert_eq!(practice.judge(&lock(0, 5, false), 10), Some(false));
+    }
+
+    #[test]
+    fn pc_minoes_follow_board_width() {
+        assert_eq!(pc_minoes(10), 10);
+        assert_eq!(pc_minoes(4), 4);
+        assert_eq!(pc_minoes(6), 6);
+    }
+}
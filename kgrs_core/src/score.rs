@@ -0,0 +1,312 @@
+//! Guideline scoring
+
+use crate::{
+    event::{GameOverEvent, LockEvent, ResetGameEvent},
+    mode::GameMode,
+};
+use bevy::prelude::*;
+use kgrs_config::profile::ActiveProfile;
+
+pub struct ScorePlugin;
+
+impl Plugin for ScorePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Score>()
+            .init_resource::<GameResults>()
+            .add_system(update_score);
+    }
+}
+
+/// Score of the current game and the states affecting it
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Score {
+    /// Total points
+    pub points: u64,
+    /// Total cleared lines
+    pub lines: usize,
+    /// Number of consecutive line clears minus one.
+    /// `None` if the last mino didn't clear any line.
+    pub combo: Option<u32>,
+    /// Whether the last line clear was a difficult clear (Tetris or T-spin).
+    pub b2b: bool,
+    /// Total lines of garbage sent
+    pub attack: u32,
+}
+
+impl Score {
+    /// Returns the current level (1-indexed).
+    /// The level increases every 10 lines.
+    pub fn level(&self) -> u64 {
+        self.lines as u64 / 10 + 1
+    }
+
+    /// Returns the score after the mino was locked.
+    pub fn after_lock(self, lock: &LockEvent) -> Self {
+        let level = self.level();
+        let lines = lock.lines;
+        let spin = lock.spin;
+        let is_difficult = lines == 4 || (spin != Spin::No && 0 < lines);
+        let is_b2b = self.b2b && is_difficult;
+
+        let mut points = drop_points(lock.soft_drop_distance, lock.drop_distance);
+
+        let clear = clear_points(lines, spin) * level;
+        points += if is_b2b { clear * 3 / 2 } else { clear };
+
+        let combo = if 0 < lines {
+            Some(self.combo.map_or(0, |c| c + 1))
+        } else {
+            None
+        };
+        points += 50 * combo.unwrap_or(0) as u64 * level;
+
+        if lock.perfect_clear {
+            points += perfect_clear_points(lines, is_b2b) * level;
+        }
+
+        let attack = attack_lines(lines, spin, is_b2b, combo, lock.perfect_clear);
+
+        Self {
+            points: self.points + points,
+            lines: self.lines + lines,
+            combo,
+            // T-spins without line clears don't break the B2B.
+            b2b: if 0 < lines { is_difficult } else { self.b2b },
+            attack: self.attack + attack,
+        }
+    }
+}
+
+/// Kind of T-spin
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Spin {
+    /// Not a T-spin.
+    #[default]
+    No,
+    /// T-spin mini
+    Mini,
+    /// T-spin
+    Full,
+}
+
+/// Returns the points for soft and hard drops.
+pub fn drop_points(soft_drop_distance: usize, hard_drop_distance: usize) -> u64 {
+    soft_drop_distance as u64 + hard_drop_distance as u64 * 2
+}
+
+/// Returns the base points of a line clear before multiplying by the level.
+pub fn clear_points(lines: usize, spin: Spin) -> u64 {
+    match (spin, lines) {
+        (Spin::No, 0) => 0,
+        (Spin::No, 1) => 100,
+        (Spin::No, 2) => 300,
+        (Spin::No, 3) => 500,
+        (Spin::No, _) => 800,
+        (Spin::Mini, 0) => 100,
+        (Spin::Mini, 1) => 200,
+        (Spin::Mini, _) => 400,
+        (Spin::Full, 0) => 400,
+        (Spin::Full, 1) => 800,
+        (Spin::Full, 2) => 1200,
+        (Spin::Full, _) => 1600,
+    }
+}
+
+/// Returns the bonus points of a perfect clear before multiplying by the level.
+pub fn perfect_clear_points(lines: usize, is_b2b: bool) -> u64 {
+    match lines {
+        0 => 0,
+        1 => 800,
+        2 => 1200,
+        3 => 1800,
+        _ if is_b2b => 3200,
+        _ => 2000,
+    }
+}
+
+/// Returns the lines of garbage sent by a line clear.
+pub fn attack_lines(
+    lines: usize,
+    spin: Spin,
+    is_b2b: bool,
+    combo: Option<u32>,
+    perfect_clear: bool,
+) -> u32 {
+    if lines == 0 {
+        return 0;
+    }
+
+    let base = match (spin, lines) {
+        (Spin::No, 1) => 0,
+        (Spin::No, 2) => 1,
+        (Spin::No, 3) => 2,
+        (Spin::No, _) => 4,
+        (Spin::Mini, 1) => 0,
+        (Spin::Mini, _) => 1,
+        (Spin::Full, 1) => 2,
+        (Spin::Full, 2) => 4,
+        (Spin::Full, _) => 6,
+    };
+    let combo = match combo.unwrap_or(0) {
+        0 => 0,
+        1 | 2 => 1,
+        3 | 4 => 2,
+        5 | 6 => 3,
+        7..=9 => 4,
+        _ => 5,
+    };
+    let pc = if perfect_clear { 10 } else { 0 };
+    base + combo + u32::from(is_b2b) + pc
+}
+
+/// Result of a finished game
+#[derive(Clone, Copy, Debug)]
+pub struct GameResult {
+    /// Game mode played.
+    pub mode: GameMode,
+    /// Final score.
+    pub score: Score,
+}
+
+/// Results of the finished games, the oldest first
+#[derive(Resource, Default)]
+pub struct GameResults(pub Vec<GameResult>);
+
+/// Adds the points of the locked minoes and stores the result when the game is over.
+/// The best score of the mode is recorded to the selected profile.
+pub fn update_score(
+    mode: Res<GameMode>,
+    mut score: ResMut<Score>,
+    mut results: ResMut<GameResults>,
+    mut profile: ResMut<ActiveProfile>,
+    mut lock_reader: EventReader<LockEvent>,
+    mut reset_reader: EventReader<ResetGameEvent>,
+    mut game_over_reader: EventReader<GameOverEvent>,
+) {
+    if reset_reader.iter().any(|reset| !reset.keep_seed) {
+        *score = Score::default();
+    }
+
+    for lock in lock_reader.iter() {
+        *score = score.after_lock(lock);
+    }
+
+    if game_over_reader.iter().next().is_some() {
+        info!("Score: {} ({} lines)", score.points, score.lines);
+        results.0.push(GameResult {
+            mode: *mode,
+            score: *score,
+        });
+
+        if let Some(profile) = &mut profile.0 {
+            let best = profile
+                .records
+                .best_scores
+                .entry(mode.to_string())
+                .or_default();
+            if *best < score.points {
+                info!("New best score of {}", *mode);
+                *best = score.points;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn lock(lines: usize) -> LockEvent {
+        LockEvent {
+            drop_distance: 0,
+            soft_drop_distance: 0,
+            spin: Spin::No,
+            lines,
+            stack_height: 0,
+            perfect_clear: false,
+        }
+    }
+
+    fn spin(lines: usize, spin: Spin) -> LockEvent {
+        LockEvent {
+            spin,
+            ..lock(lines)
+        }
+    }
+
+    #[test]
+    fn drop_points_per_cell() {
+        let mut l = lock(0);
+        l.drop_distance = 10;
+        assert_eq!(Score::default().after_lock(&l).points, 20);
+        l.soft_drop_distance = 3;
+        assert_eq!(Score::default().after_lock(&l).points, 23);
+    }
+
+    #[test]
+    fn line_clears_are_multiplied_by_level() {
+        let score = Score {
+            lines: 25,
+            ..default()
+        };
+        assert_eq!(score.level(), 3);
+        assert_eq!(score.after_lock(&lock(3)).points, 1500);
+        assert_eq!(score.after_lock(&spin(2, Spin::Full)).points, 3600);
+        assert_eq!(score.after_lock(&spin(0, Spin::Mini)).points, 300);
+    }
+
+    #[test]
+    fn combo_adds_points_and_breaks() {
+        let mut score = Score::default();
+        for _ in 0..3 {
+            score = score.after_lock(&lock(1));
+        }
+        // 100 + (100 + 50) + (100 + 100)
+        assert_eq!(score.points, 450);
+        assert_eq!(score.combo, Some(2));
+
+        score = score.after_lock(&lock(0));
+        assert_eq!(score.combo, None);
+    }
+
+    #[test]
+    fn back_to_back_difficult_clears() {
+        let mut score = Score::default().after_lock(&lock(4));
+        assert!(score.b2b);
+        assert_eq!(score.points, 800);
+
+        // A T-spin without lines keeps the B2B.
+        score = score.after_lock(&spin(0, Spin::Full));
+        assert!(score.b2b);
+        assert_eq!(score.points, 1200);
+
+        // 1.5x of T-spin double
+        score = score.after_lock(&spin(2, Spin::Full));
+        assert_eq!(score.points, 3000);
+
+        score = score.after_lock(&lock(1));
+        assert!(!score.b2b);
+    }
+
+    #[test]
+    fn attack_of_line_clears() {
+        assert_eq!(attack_lines(1, Spin::No, false, None, false), 0);
+        assert_eq!(attack_lines(4, Spin::No, true, Some(0), false), 5);
+        assert_eq!(attack_lines(2, Spin::Full, false, Some(3), false), 6);
+        assert_eq!(attack_lines(4, Spin::No, false, Some(0), true), 14);
+        assert_eq!(attack_lines(0, Spin::Full, true, None, false), 0);
+    }
+
+    #[test]
+    fn perfect_clear_bonus() {
+        let mut l = lock(4);
+        l.perfect_clear = true;
+        assert_eq!(Score::default().after_lock(&l).points, 800 + 2000);
+
+        let score = Score {
+            b2b: true,
+            ..default()
+        };
+        assert_eq!(score.after_lock(&l).points, 1200 + 3200);
+    }
+}
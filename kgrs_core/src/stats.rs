@@ -0,0 +1,70 @@
+//! Statistics of the current game
+
+use crate::{
+    event::{LockEvent, ResetGameEvent},
+    mino::control::MinoCtrl,
+};
+use bevy::{prelude::*, time::Stopwatch};
+use kgrs_util::state::AppState;
+
+pub struct StatsPlugin;
+
+impl Plugin for StatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameStats>().add_system(update_stats);
+    }
+}
+
+/// Statistics of the current game
+#[derive(Resource, Default)]
+pub struct GameStats {
+    /// Elapsed time since the game started.
+    pub time: Stopwatch,
+    /// Number of the placed minoes.
+    pub pieces: usize,
+    /// Seed for the RNG of the minoes.
+    pub seed: u64,
+}
+
+impl GameStats {
+    /// Returns Pieces Per Second.
+    pub fn pps(&self) -> f32 {
+        per_time(self.pieces as f32, self.time.elapsed_secs())
+    }
+
+    /// Returns Attack Per Minute from the total attack.
+    pub fn apm(&self, attack: u32) -> f32 {
+        per_time(attack as f32, self.time.elapsed_secs() / 60.)
+    }
+}
+
+/// Divides `amount` by `time` but returns 0.0 if no time has elapsed.
+fn per_time(amount: f32, time: f32) -> f32 {
+    if time <= 0. {
+        0.
+    } else {
+        amount / time
+    }
+}
+
+/// Counts up the time while in game and the placed minoes.
+fn update_stats(
+    time: Res<Time>,
+    state: Res<State<AppState>>,
+    mut stats: ResMut<GameStats>,
+    mut lock_reader: EventReader<LockEvent>,
+    mut reset_reader: EventReader<ResetGameEvent>,
+    mino_ctrl_query: Query<&MinoCtrl>,
+) {
+    if reset_reader.iter().any(|reset| !reset.keep_seed) {
+        *stats = GameStats::default();
+    }
+
+    if state.current() == &AppState::InGame {
+        stats.time.tick(time.delta());
+    }
+    stats.pieces += lock_reader.iter().len();
+    if let Ok(mino_ctrl) = mino_ctrl_query.get_single() {
+        stats.seed = mino_ctrl.seed;
+    }
+}
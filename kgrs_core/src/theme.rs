@@ -0,0 +1,483 @@
+//! Themes of the board and the minoes
+//!
+//! A theme is a JSON or TOML file in `assets/themes` selected by `Config::theme`.
+//! The JSON one is read if both exist.
+//! The colors are written as hex codes like `"#14100b"`
+//! and the missing ones are taken from the default theme,
+//! which is made of the constants in `kgrs_const::color`.
+//!
+//! A theme can also have a skin: a sprite sheet with a square block
+//! for each kind of mino in a row, in the order of `SKIN_ORDER`.
+//!
+//! The colour-blind palette in `Config::color_blind` replaces the colors
+//! and the skin of the minoes of any theme.
+
+use crate::{
+    board::{set_grid_opacity, Board, Frame, Grid, CELL_SIZE},
+    mino::{
+        glyph::{glyph_color, Glyph},
+        mesh::MinoInfo,
+        MinoType,
+    },
+};
+use bevy::{
+    asset::FileAssetIo, prelude::*, render::mesh::VertexAttributeValues, sprite::Mesh2dHandle,
+};
+use kgrs_config::{ColorBlind, Config, ConfigPreview};
+use kgrs_const::color::*;
+use kgrs_util::function::path::is_file_name;
+use serde::{Deserialize, Serialize};
+use serde_json::from_slice;
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Directory of the themes in the assets directory
+const THEMES_DIR: &str = "themes";
+
+/// Extensions of the theme files in the order they are looked for
+const THEME_EXTENSIONS: [&str; 2] = ["json", "toml"];
+
+/// Name of the built-in theme
+pub const DEFAULT_THEME: &str = "default";
+
+/// Kinds of minoes in the order of the blocks in a skin
+pub(crate) const SKIN_ORDER: [MinoType; 8] = [
+    MinoType::I,
+    MinoType::O,
+    MinoType::L,
+    MinoType::J,
+    MinoType::Z,
+    MinoType::S,
+    MinoType::T,
+    MinoType::Garbage,
+];
+
+pub struct ThemePlugin;
+
+impl Plugin for ThemePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Theme>()
+            .init_resource::<ConfigPreview>()
+            .insert_resource(ThemeList(Theme::list()))
+            .add_system(load_theme)
+            .add_system(apply_theme.after(load_theme));
+    }
+}
+
+/// Colors and the skin of the board and the minoes
+#[derive(Resource, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(default)]
+pub struct Theme {
+    /// Background of the window and the menus
+    #[serde(with = "hex")]
+    pub background: Color,
+    #[serde(with = "hex")]
+    pub board: Color,
+    #[serde(with = "hex")]
+    pub grid: Color,
+    /// Frame of the board
+    #[serde(with = "hex")]
+    pub frame: Color,
+    #[serde(with = "hex")]
+    pub hud_text: Color,
+    pub minoes: MinoColors,
+    /// Sprite sheet of the blocks in the assets directory
+    pub skin: Option<String>,
+    /// Texture of `skin`
+    #[serde(skip)]
+    pub skin_texture: Option<Handle<Image>>,
+}
+
+/// Colors of each kind of mino
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq)]
+#[serde(default)]
+pub struct MinoColors {
+    #[serde(rename = "I", with = "hex")]
+    pub i: Color,
+    #[serde(rename = "O", with = "hex")]
+    pub o: Color,
+    #[serde(rename = "L", with = "hex")]
+    pub l: Color,
+    #[serde(rename = "J", with = "hex")]
+    pub j: Color,
+    #[serde(rename = "Z", with = "hex")]
+    pub z: Color,
+    #[serde(rename = "S", with = "hex")]
+    pub s: Color,
+    #[serde(rename = "T", with = "hex")]
+    pub t: Color,
+    #[serde(rename = "Garbage", with = "hex")]
+    pub garbage: Color,
+}
+
+impl Default for Theme {
+    fn default() -> Self {
+        Self {
+            background: BG_COL,
+            board: BOARD_COL,
+            grid: GRID_COL,
+            frame: FRAME_COL,
+            hud_text: HUD_TEXT_COL,
+            minoes: MinoColors::default(),
+            skin: None,
+            skin_texture: None,
+        }
+    }
+}
+
+impl MinoColors {
+    /// Returns the colors of the colour-blind palette.
+    ///
+    /// Returns `None` with `ColorBlind::Off`.
+    pub fn for_color_blind(color_blind: ColorBlind) -> Option<Self> {
+        let hexes = match color_blind {
+            ColorBlind::Off => return None,
+            // Based on the Okabe-Ito palette.
+            // Z and S, L and O differ in lightness as well as in hue.
+            ColorBlind::Protanopia => [
+                "56b4e9", "f0e442", "e69f00", "0072b2", "ff7a4d", "009e73", "cc79a7", "8c8c8c",
+            ],
+            ColorBlind::Deuteranopia => [
+                "56b4e9", "f0e442", "e69f00", "0072b2", "d55e00", "009e73", "cc79a7", "8c8c8c",
+            ],
+            // Tritanopia confuses blue with green and yellow with violet.
+            ColorBlind::Tritanopia => [
+                "00a5b5", "ffd1dc", "e36414", "1b3b6f", "d7263d", "8fbf3f", "6a0dad", "9e9e9e",
+            ],
+            ColorBlind::HighContrast => [
+                "00ffff", "ffff00", "ff8000", "0040ff", "ff0040", "00ff40", "c000ff", "ffffff",
+            ],
+        };
+        let [i, o, l, j, z, s, t, garbage] = hexes.map(|hex| Color::hex(hex).unwrap());
+        Some(Self {
+            i,
+            o,
+            l,
+            j,
+            z,
+            s,
+            t,
+            garbage,
+        })
+    }
+}
+
+impl Default for MinoColors {
+    fn default() -> Self {
+        Self {
+            i: mino_color::I,
+            o: mino_color::O,
+            l: mino_color::L,
+            j: mino_color::J,
+            z: mino_color::Z,
+            s: mino_color::S,
+            t: mino_color::T,
+            garbage: mino_color::GARBAGE,
+        }
+    }
+}
+
+/// Names of the available themes, the default one first
+#[derive(Resource)]
+pub struct ThemeList(pub Vec<String>);
+
+impl Theme {
+    /// Reads the theme from the themes directory.
+    /// The default theme is built in.
+    ///
+    /// A name which would reach out of the themes directory is rejected.
+    pub fn load(name: &str) -> io::Result<Self> {
+        if name == DEFAULT_THEME {
+            return Ok(Self::default());
+        }
+        if !is_file_name(name) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid theme name {:?}", name),
+            ));
+        }
+        let dir = themes_dir();
+        let file = THEME_EXTENSIONS
+            .iter()
+            .map(|ext| dir.join(format!("{}.{}", name, ext)))
+            .find(|file| file.is_file())
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no such theme"))?;
+        let bytes = fs::read(&file)?;
+        if file.extension().is_some_and(|ext| ext == "toml") {
+            let text = std::str::from_utf8(&bytes)
+                .map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))?;
+            toml::from_str(text).map_err(|why| io::Error::new(io::ErrorKind::InvalidData, why))
+        } else {
+            Ok(from_slice(&bytes)?)
+        }
+    }
+
+    /// Returns the names of the themes in the themes directory
+    /// after the default theme.
+    pub fn list() -> Vec<String> {
+        let mut names: Vec<_> = match fs::read_dir(themes_dir()) {
+            Ok(entries) => entries
+                .filter_map(|entry| {
+                    let path = entry.ok()?.path();
+                    if !THEME_EXTENSIONS.contains(&path.extension()?.to_str()?) {
+                        return None;
+                    }
+                    Some(path.file_stem()?.to_str()?.to_string())
+                })
+                .filter(|name| name != DEFAULT_THEME && is_file_name(name))
+                .collect(),
+            Err(_) => Vec::new(),
+        };
+        names.sort();
+        names.dedup();
+        names.insert(0, String::from(DEFAULT_THEME));
+        names
+    }
+
+    /// Returns the color of the mino.
+    pub(crate) fn mino_color(&self, kind: MinoType) -> Color {
+        let colors = &self.minoes;
+        match kind {
+            MinoType::I => colors.i,
+            MinoType::O => colors.o,
+            MinoType::L => colors.l,
+            MinoType::J => colors.j,
+            MinoType::Z => colors.z,
+            MinoType::S => colors.s,
+            MinoType::T => colors.t,
+            MinoType::Garbage => colors.garbage,
+        }
+    }
+
+    /// Returns the material of a block of the mino.
+    /// The skin is drawn as it is, without the color.
+    pub(crate) fn block_material(&self, kind: MinoType) -> ColorMaterial {
+        match &self.skin_texture {
+            Some(texture) => ColorMaterial {
+                color: Color::WHITE,
+                texture: Some(texture.clone()),
+            },
+            None => ColorMaterial::from(self.mino_color(kind)),
+        }
+    }
+
+    /// Returns the square mesh of a block of the mino
+    /// mapped to the block of the mino in the skin.
+    pub(crate) fn block_mesh(&self, kind: MinoType, size: f32) -> Mesh {
+        let mut mesh = Mesh::from(shape::Quad {
+            size: Vec2::splat(size),
+            ..default()
+        });
+        if self.skin_texture.is_some() {
+            let index = SKIN_ORDER.iter().position(|k| *k == kind).unwrap_or(0);
+            if let Some(VertexAttributeValues::Float32x2(uvs)) =
+                mesh.attribute_mut(Mesh::ATTRIBUTE_UV_0)
+            {
+                for uv in uvs {
+                    uv[0] = (index as f32 + uv[0]) / SKIN_ORDER.len() as f32;
+                }
+            }
+        }
+        mesh
+    }
+}
+
+/// Returns the themes directory in the assets directory.
+fn themes_dir() -> PathBuf {
+    FileAssetIo::get_base_path().join("assets").join(THEMES_DIR)
+}
+
+/// Loads the theme selected in the config when it or the colour-blind palette is changed.
+///
+/// The default theme is used if the theme can't be read.
+/// The theme in the settings screen is previewed before it is applied.
+fn load_theme(
+    config: Res<Config>,
+    preview: Res<ConfigPreview>,
+    asset_server: Res<AssetServer>,
+    mut current: Local<Option<(String, ColorBlind)>>,
+    mut theme: ResMut<Theme>,
+) {
+    if !config.is_changed() && !preview.is_changed() {
+        return;
+    }
+    let config = preview.or(&config);
+    let selected = (config.theme.clone(), config.color_blind);
+    if current.as_ref() == Some(&selected) {
+        return;
+    }
+    *current = Some(selected);
+
+    let mut loaded = Theme::load(&config.theme).unwrap_or_else(|why| {
+        warn!("Failed to load theme {}: {}", config.theme, why);
+        Theme::default()
+    });
+    if let Some(colors) = MinoColors::for_color_blind(config.color_blind) {
+        // The colors of a skin can't be replaced.
+        loaded.minoes = colors;
+        loaded.skin = None;
+    }
+    loaded.skin_texture = loaded
+        .skin
+        .as_ref()
+        .map(|skin| asset_server.load(Path::new(skin)));
+    info!(
+        "Loaded theme {} ({} palette)",
+        config.theme, config.color_blind
+    );
+    if *theme != loaded {
+        *theme = loaded;
+    }
+}
+
+/// Applies the theme to the existing board and minoes when it is changed.
+#[allow(clippy::too_many_arguments)]
+fn apply_theme(
+    theme: Res<Theme>,
+    config: Res<Config>,
+    preview: Res<ConfigPreview>,
+    mut clear_color: ResMut<ClearColor>,
+    board_query: Query<&Handle<ColorMaterial>, With<Board>>,
+    frame_query: Query<&Handle<ColorMaterial>, With<Frame>>,
+    grid_query: Query<&Handle<ColorMaterial>, With<Grid>>,
+    mut block_query: Query<(&MinoInfo, &Handle<ColorMaterial>, &mut Mesh2dHandle)>,
+    glyph_query: Query<(&Parent, &Handle<ColorMaterial>), With<Glyph>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+) {
+    if !theme.is_changed() {
+        return;
+    }
+    clear_color.0 = theme.background;
+    let mut set_color = |handle: &Handle<ColorMaterial>, color: Color| {
+        if let Some(material) = materials.get_mut(handle) {
+            material.color = color;
+        }
+    };
+    for handle in board_query.iter() {
+        set_color(handle, theme.board);
+    }
+    for handle in frame_query.iter() {
+        set_color(handle, theme.frame);
+    }
+    let grid_opacity = preview.or(&config).grid_opacity;
+    set_grid_opacity(grid_opacity, &theme, &grid_query, &mut materials);
+
+    for (info, material, mut mesh) in block_query.iter_mut() {
+        if let Some(material) = materials.get_mut(material) {
+            *material = theme.block_material(info.kind);
+        }
+        mesh.0 = meshes.add(theme.block_mesh(info.kind, CELL_SIZE));
+    }
+    for (parent, material) in glyph_query.iter() {
+        if let (Ok((info, ..)), Some(material)) =
+            (block_query.get(parent.get()), materials.get_mut(material))
+        {
+            material.color = glyph_color(theme.mino_color(info.kind));
+        }
+    }
+}
+
+/// (De)serializes colors as hex codes like `"#55ddff"`.
+mod hex {
+    use bevy::prelude::Color;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub(super) fn serialize<S: Serializer>(
+        color: &Color,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let [r, g, b, a] = color.as_rgba_f32().map(|c| (c * 255.).round() as u8);
+        let hex = if a == 255 {
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        } else {
+            format!("#{:02x}{:02x}{:02x}{:02x}", r, g, b, a)
+        };
+        serializer.serialize_str(&hex)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Color, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        Color::hex(hex.trim_start_matches('#'))
+            .map_err(|why| D::Error::custom(format!("invalid color {}: {:?}", hex, why)))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn partial_theme_falls_back_to_default() {
+        let theme: Theme = serde_json::from_str(
+            r##"{ "board": "#000000", "minoes": { "Z": "#ff0000" }, "skin": "skins/a.png" }"##,
+        )
+        .unwrap();
+        let default = Theme::default();
+        assert_eq!(theme.board, Color::rgb(0., 0., 0.));
+        assert_eq!(theme.minoes.z, Color::rgb(1., 0., 0.));
+        assert_eq!(theme.minoes.s, default.minoes.s);
+        assert_eq!(theme.background, default.background);
+        assert_eq!(theme.skin.as_deref(), Some("skins/a.png"));
+    }
+
+    #[test]
+    fn colors_round_trip_as_hex() {
+        let json = serde_json::to_string(&Theme::default()).unwrap();
+        assert!(json.contains(r##""board":"#14100b""##));
+        let theme: Theme = serde_json::from_str(&json).unwrap();
+        assert_eq!(theme.minoes.i, Color::hex("55ddff").unwrap());
+        assert!(serde_json::from_str::<Theme>(r#"{ "grid": "white" }"#).is_err());
+    }
+
+    #[test]
+    fn theme_in_toml() {
+        let theme: Theme = toml::from_str(
+            r##"
+            board = "#000000"
+
+            [minoes]
+            Z = "#ff0000"
+            "##,
+        )
+        .unwrap();
+        assert_eq!(theme.board, Color::rgb(0., 0., 0.));
+        assert_eq!(theme.minoes.z, Color::rgb(1., 0., 0.));
+        assert_eq!(theme.minoes.s, Theme::default().minoes.s);
+    }
+
+    #[test]
+    fn theme_name_stays_in_themes_dir() {
+        for name in ["", "../config", "skins/a", "a\\b", ".."] {
+            assert_eq!(
+                Theme::load(name).err().map(|why| why.kind()),
+                Some(io::ErrorKind::InvalidInput)
+            );
+        }
+    }
+
+    #[test]
+    fn color_blind_palettes() {
+        assert!(MinoColors::for_color_blind(ColorBlind::Off).is_none());
+        for color_blind in &ColorBlind::ALL[1..] {
+            let colors = MinoColors::for_color_blind(*color_blind).unwrap();
+            let all = [
+                colors.i,
+                colors.o,
+                colors.l,
+                colors.j,
+                colors.z,
+                colors.s,
+                colors.t,
+                colors.garbage,
+            ];
+            for (n, a) in all.iter().enumerate() {
+                assert!(!all[n + 1..].contains(a), "{}", color_blind);
+            }
+        }
+    }
+}
@@ -4,7 +4,17 @@ use bevy::{
     window::PresentMode,
 };
 use bevy_egui::{egui, EguiContext};
-use kgrs_config::{Config, WindowModeForConf};
+use kgrs_config::{binding::Action, profile::ActiveProfile, Config, WindowModeForConf};
+use kgrs_core::{
+    event::LoadBoardEvent,
+    input::Actions,
+    mino::state::{BoardState, CurrentBoardState},
+    mode::{
+        pc_practice::{PcPractice, PcStats},
+        GameMode,
+    },
+    score::Score,
+};
 use kgrs_util::function::fmt::wm_to_string;
 
 pub struct DebugUiPlugin;
@@ -26,24 +36,46 @@ fn startup_debug_ui(mut cmds: Commands) {
 struct DebugUi {
     /// Whether the debug window is open
     open: bool,
+    /// Board state in text or JSON to export or import
+    board_text: String,
+    /// Result of the last import
+    board_message: String,
 }
 
 impl DebugUi {
     fn init() -> Self {
-        Self { open: false }
+        Self {
+            open: false,
+            board_text: String::new(),
+            board_message: String::new(),
+        }
     }
 }
 
+#[allow(clippy::too_many_arguments)]
 fn debug_ui(
     mut egui_ctx: ResMut<EguiContext>,
     diags: Res<Diagnostics>,
     mut windows: ResMut<Windows>,
     mut query: Query<&mut DebugUi>,
+    mut game_mode: ResMut<GameMode>,
+    mut pc_practice: ResMut<PcPractice>,
+    profile: Res<ActiveProfile>,
+    score: Res<Score>,
+    mut config: ResMut<Config>,
+    board_state: CurrentBoardState,
+    mut load_writer: EventWriter<LoadBoardEvent>,
 ) {
     let window = windows.primary_mut();
     let enable_vsync = matches!(window.present_mode(), PresentMode::AutoVsync);
+    let mut debug_ui = query.single_mut();
+    let DebugUi {
+        open,
+        board_text,
+        board_message,
+    } = &mut *debug_ui;
     egui::Window::new("Debug")
-        .open(&mut query.single_mut().open)
+        .open(open)
         .show(egui_ctx.ctx_mut(), |ui| {
             ui.heading("Performance");
             ui.label(format!(
@@ -60,10 +92,7 @@ fn debug_ui(
                 } else {
                     PresentMode::AutoVsync
                 });
-
-                let mut config = Config::load();
                 config.vsync = !enable_vsync;
-                config.save();
 
                 info!("PRESENT_MODE: {:?}", window.present_mode());
             }
@@ -119,17 +148,79 @@ fn debug_ui(
                         wm_to_string(window_mode)
                     );
                     window.set_mode(window_mode);
-
-                    let mut config = Config::load();
                     config.window_mode = WindowModeForConf::from_window_mode(window_mode);
-                    config.save();
+                }
+            });
+            ui.collapsing("Game", |ui_g| {
+                let mut mode = *game_mode;
+                egui::ComboBox::from_id_source("GameMode")
+                    .selected_text(mode.to_string())
+                    .show_ui(ui_g, |ui_g_m| {
+                        for m in [GameMode::Free, GameMode::PcPractice].iter() {
+                            ui_g_m.selectable_value(&mut mode, *m, m.to_string());
+                        }
+                    });
+                // Don't touch the resource unless changed, to avoid restarting the game.
+                if mode != *game_mode {
+                    *game_mode = mode;
+                }
+                ui_g.label(format!(
+                    "Score: {} (Lv. {}, {} lines)",
+                    score.points,
+                    score.level(),
+                    score.lines
+                ));
+
+                if mode == GameMode::PcPractice {
+                    ui_g.horizontal(|ui_g_o| {
+                        ui_g_o.label("Opener:");
+                        ui_g_o.text_edit_singleline(&mut pc_practice.opener);
+                    });
+                    for (opener, record) in pc_practice.records(&profile) {
+                        let stats = PcStats::from(*record);
+                        ui_g.label(format!(
+                            "{}: {}/{} ({:.1}%)",
+                            opener,
+                            stats.successes,
+                            stats.attempts,
+                            stats.success_rate() * 100.
+                        ));
+                    }
+                }
+            });
+            ui.collapsing("Board state", |ui_b| {
+                ui_b.horizontal(|ui_b_b| {
+                    if ui_b_b.button("Export text").clicked() {
+                        *board_text = board_state.get().to_string();
+                        board_message.clear();
+                    }
+                    if ui_b_b.button("Export JSON").clicked() {
+                        *board_text = board_state.get().to_json();
+                        board_message.clear();
+                    }
+                    if ui_b_b.button("Import").clicked() {
+                        *board_message = match BoardState::parse(board_text, config.board) {
+                            Ok(state) if state.size() != config.board => {
+                                String::from("The board size doesn't match the settings")
+                            }
+                            Ok(state) => {
+                                load_writer.send(LoadBoardEvent { state });
+                                String::from("Imported")
+                            }
+                            Err(why) => why.to_string(),
+                        };
+                    }
+                });
+                ui_b.add(egui::TextEdit::multiline(board_text).code_editor());
+                if !board_message.is_empty() {
+                    ui_b.label(board_message.as_str());
                 }
             });
         });
 }
 
-fn toggle_debug_ui(input: Res<Input<KeyCode>>, mut query: Query<&mut DebugUi>) {
-    if input.just_pressed(KeyCode::F3) {
+fn toggle_debug_ui(actions: Actions, mut query: Query<&mut DebugUi>) {
+    if actions.just_pressed(Action::ToggleDebug) {
         for mut debug_ui in query.iter_mut() {
             debug_ui.open = !debug_ui.open;
         }
@@ -1,5 +1,6 @@
 use bevy::prelude::*;
-use kgrs_config::{Config, WindowModeForConf};
+use kgrs_config::{binding::Action, Config, WindowModeForConf};
+use kgrs_core::input::Actions;
 use kgrs_util::function::fmt::wm_to_string;
 
 pub struct ToggleFullscreenPlugin;
@@ -10,8 +11,12 @@ impl Plugin for ToggleFullscreenPlugin {
     }
 }
 
-fn toggle_fullscreen(input: Res<Input<KeyCode>>, mut windows: ResMut<Windows>) {
-    if input.just_pressed(KeyCode::F11) {
+fn toggle_fullscreen(
+    // `Actions` reads `Config` so they can't be borrowed at once.
+    mut params: ParamSet<(Actions, ResMut<Config>)>,
+    mut windows: ResMut<Windows>,
+) {
+    if params.p0().just_pressed(Action::ToggleFullscreen) {
         let window = windows.primary_mut();
         let window_mode = match window.mode() {
             // TODO: optional fullscreen or borderless fullscreen for low-end
@@ -24,9 +29,6 @@ fn toggle_fullscreen(input: Res<Input<KeyCode>>, mut windows: ResMut<Windows>) {
             wm_to_string(window_mode)
         );
         window.set_mode(window_mode);
-
-        let mut config = Config::load();
-        config.window_mode = WindowModeForConf::from_window_mode(window_mode);
-        config.save();
+        params.p1().window_mode = WindowModeForConf::from_window_mode(window_mode);
     }
 }
@@ -0,0 +1,152 @@
+use crate::style::*;
+use bevy::{ecs::schedule::ShouldRun, prelude::*};
+use bevy_egui::{egui, EguiContext};
+use kgrs_config::profile::ActiveProfile;
+use kgrs_core::{
+    layout::Layout,
+    mode::{pc_practice::PcPractice, GameMode},
+    score::Score,
+    stats::GameStats,
+    theme::Theme,
+};
+use kgrs_util::state::AppState;
+
+pub struct HudPlugin;
+
+impl Plugin for HudPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            SystemSet::new()
+                .with_run_criteria(is_board_shown)
+                .with_system(hud),
+        );
+    }
+}
+
+/// Returns the offsets of the stats from the window top-right corner
+/// and the seed from the window bottom-left corner.
+fn hud_offsets(layout: &Layout) -> (egui::Vec2, egui::Vec2) {
+    let stats = layout.to_window(layout.stats_anchor);
+    let seed = layout.to_window(layout.seed_anchor);
+    (
+        egui::vec2(stats.x - layout.window.x, stats.y),
+        egui::vec2(seed.x, seed.y - layout.window.y),
+    )
+}
+
+/// A stat shown on the HUD
+#[derive(Clone, Copy)]
+enum HudStat {
+    Time,
+    Score,
+    Lines,
+    Level,
+    Pps,
+    Apm,
+    PcRate,
+}
+
+impl HudStat {
+    /// Returns the stats shown in the game mode, from the top.
+    fn for_mode(mode: GameMode) -> &'static [Self] {
+        match mode {
+            GameMode::Free => &[
+                Self::Time,
+                Self::Score,
+                Self::Lines,
+                Self::Level,
+                Self::Pps,
+                Self::Apm,
+            ],
+            GameMode::PcPractice => &[Self::Time, Self::PcRate, Self::Lines, Self::Pps],
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Time => "TIME",
+            Self::Score => "SCORE",
+            Self::Lines => "LINES",
+            Self::Level => "LEVEL",
+            Self::Pps => "PPS",
+            Self::Apm => "APM",
+            Self::PcRate => "PC RATE",
+        }
+    }
+}
+
+/// Returns `ShouldRun::Yes` on the screens showing the board.
+fn is_board_shown(state: Res<State<AppState>>) -> ShouldRun {
+    match state.current() {
+        AppState::InGame | AppState::Paused | AppState::Results => ShouldRun::Yes,
+        _ => ShouldRun::No,
+    }
+}
+
+/// Draws the HUD around the board.
+#[allow(clippy::too_many_arguments)]
+fn hud(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    stats: Res<GameStats>,
+    pc_practice: Res<PcPractice>,
+    profile: Res<ActiveProfile>,
+    theme: Res<Theme>,
+) {
+    let text_col = egui_color(theme.hud_text);
+    let font_size = layout.hud_font_size;
+    let label_size = font_size * 0.6;
+    let (stats_offset, seed_offset) = hud_offsets(&layout);
+    let ctx = egui_ctx.ctx_mut();
+
+    egui::Area::new("HudStats")
+        .anchor(egui::Align2::RIGHT_TOP, stats_offset)
+        .interactable(false)
+        .show(ctx, |ui| {
+            ui.with_layout(egui::Layout::top_down(egui::Align::Max), |ui_s| {
+                for stat in HudStat::for_mode(*mode) {
+                    let value = match stat {
+                        HudStat::Time => format_time(stats.time.elapsed_secs()),
+                        HudStat::Score => score.points.to_string(),
+                        HudStat::Lines => score.lines.to_string(),
+                        HudStat::Level => score.level().to_string(),
+                        HudStat::Pps => format!("{:.2}", stats.pps()),
+                        HudStat::Apm => format!("{:.1}", stats.apm(score.attack)),
+                        HudStat::PcRate => {
+                            let pc_stats = pc_practice.stats(&profile);
+                            format!(
+                                "{:.1}% ({}/{})",
+                                pc_stats.success_rate() * 100.,
+                                pc_stats.successes,
+                                pc_stats.attempts
+                            )
+                        }
+                    };
+                    ui_s.label(
+                        egui::RichText::new(stat.label())
+                            .size(label_size)
+                            .color(text_col),
+                    );
+                    ui_s.label(
+                        egui::RichText::new(value)
+                            .size(font_size)
+                            .color(text_col)
+                            .strong(),
+                    );
+                }
+            });
+        });
+
+    egui::Area::new("HudSeed")
+        .anchor(egui::Align2::LEFT_BOTTOM, seed_offset)
+        .interactable(false)
+        .show(ctx, |ui| {
+            ui.label(
+                egui::RichText::new(format!("SEED {:0>9}", stats.seed))
+                    .size(label_size)
+                    .color(text_col),
+            );
+        });
+}
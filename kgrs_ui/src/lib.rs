@@ -1 +1,5 @@
 //! User Interface for KaGRiS
+
+pub mod hud;
+pub mod menu;
+pub(crate) mod style;
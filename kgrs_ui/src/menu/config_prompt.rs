@@ -0,0 +1,105 @@
+use super::*;
+use crate::style::*;
+use bevy_egui::{egui, EguiContext};
+use kgrs_config::{validation::ConfigIssues, Config};
+use kgrs_core::{layout::Layout, theme::Theme};
+
+pub(crate) struct ConfigPromptPlugin;
+
+impl Plugin for ConfigPromptPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::ConfigPrompt).with_system(reset_cursor))
+            .add_system_set(
+                SystemSet::on_update(AppState::ConfigPrompt).with_system(config_prompt),
+            );
+    }
+}
+
+/// Items of the config prompt
+#[derive(Clone, Copy)]
+enum PromptItem {
+    Reset,
+    Keep,
+    OpenFile,
+    Reload,
+}
+
+impl PromptItem {
+    const ALL: [Self; 4] = [Self::Reset, Self::Keep, Self::OpenFile, Self::Reload];
+
+    fn label(&self) -> String {
+        match self {
+            Self::Reset => "Reset to Default",
+            Self::Keep => "Keep Valid Settings",
+            Self::OpenFile => "Open config.json",
+            Self::Reload => "Reload",
+        }
+        .to_string()
+    }
+}
+
+/// Screen listing the problems of config.json
+#[allow(clippy::too_many_arguments)]
+fn config_prompt(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut config: ResMut<Config>,
+    mut issues: ResMut<ConfigIssues>,
+    theme: Res<Theme>,
+) {
+    let window_height = layout.window.y;
+    let font_size = layout.menu_font_size;
+    let mut is_confirmed = cursor.navigate(&mut input, PromptItem::ALL.len());
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui_color(theme.background)))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui_c| {
+                ui_c.add_space(window_height * 0.1);
+                ui_c.label(egui::RichText::new("config.json has problems").size(font_size * 1.5));
+                ui_c.add_space(window_height * 0.03);
+                for issue in &issues.0 {
+                    ui_c.label(issue.to_string());
+                }
+                ui_c.label("The invalid settings were reset to the default.");
+                ui_c.add_space(window_height * 0.05);
+
+                let labels = PromptItem::ALL.map(|item| item.label());
+                if let Some(i) = menu_items(ui_c, &labels, cursor.0, font_size) {
+                    cursor.0 = i;
+                    is_confirmed = true;
+                }
+            });
+        });
+
+    if is_confirmed {
+        match PromptItem::ALL[cursor.0] {
+            PromptItem::Reset => {
+                info!("Resetting config");
+                Config::backup("invalid");
+                // Written back by `ConfigBufferPlugin`.
+                *config = Config::default();
+                issues.0.clear();
+                go_to(&mut state, AppState::ProfileSelect);
+            }
+            PromptItem::Keep => {
+                // The file is left as is until the config is changed.
+                issues.0.clear();
+                go_to(&mut state, AppState::ProfileSelect);
+            }
+            PromptItem::OpenFile => Config::open_file(),
+            PromptItem::Reload => {
+                info!("Reloading config");
+                let (reloaded, reloaded_issues) = Config::load_checked();
+                *config = reloaded;
+                issues.0 = reloaded_issues;
+                if issues.0.is_empty() {
+                    go_to(&mut state, AppState::ProfileSelect);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use super::settings::SettingsDraft;
+use super::*;
+use crate::style::*;
+use bevy_egui::{egui, EguiContext};
+use kgrs_config::{
+    binding::{Action, Bindings, ButtonBindings, KeyBindings},
+    Config,
+};
+use kgrs_core::{layout::Layout, theme::Theme};
+use std::fmt::Debug;
+
+pub(crate) struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Rebinding>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Controls)
+                    .with_system(reset_cursor)
+                    .with_system(reset_rebinding),
+            )
+            .add_system_set(SystemSet::on_update(AppState::Controls).with_system(controls));
+    }
+}
+
+/// State of rebinding the keys and the gamepad buttons
+#[derive(Resource, Default)]
+struct Rebinding {
+    /// Action waiting for the next key or button press.
+    capturing: Option<Action>,
+    /// Whether Esc was pressed while capturing.
+    /// Esc cancels the capture unless it is pressed again to be bound.
+    is_escape_pending: bool,
+    /// Result of the last rebinding.
+    message: String,
+}
+
+/// Items of the controls menu
+#[derive(Clone, Copy)]
+enum ControlsItem {
+    Action(Action),
+    ResetDefault,
+    Back,
+}
+
+impl ControlsItem {
+    fn all() -> Vec<Self> {
+        let mut items: Vec<_> = Action::ALL.into_iter().map(Self::Action).collect();
+        items.push(Self::ResetDefault);
+        items.push(Self::Back);
+        items
+    }
+
+    fn label(&self) -> String {
+        match self {
+            Self::Action(action) => action.to_string(),
+            Self::ResetDefault => String::from("Reset to Default"),
+            Self::Back => String::from("Back"),
+        }
+    }
+}
+
+fn reset_rebinding(mut rebinding: ResMut<Rebinding>) {
+    *rebinding = Rebinding::default();
+}
+
+/// Controls screen to rebind the keys and the buttons of the actions
+#[allow(clippy::too_many_arguments)]
+fn controls(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    buttons: Res<Input<GamepadButton>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut rebinding: ResMut<Rebinding>,
+    mut settings: ResMut<SettingsDraft>,
+    theme: Res<Theme>,
+) {
+    let items = ControlsItem::all();
+    let mut is_confirmed = false;
+
+    if let Some(action) = rebinding.capturing {
+        capture_input(
+            action,
+            &mut input,
+            &buttons,
+            &mut rebinding,
+            &mut settings.0,
+        );
+    } else {
+        is_confirmed = cursor.navigate(&mut input, items.len());
+        if is_back_pressed(&mut input) {
+            back_to_settings(&mut state);
+            return;
+        }
+        let is_delete_pressed =
+            input.clear_just_pressed(KeyCode::Delete) | input.clear_just_pressed(KeyCode::Back);
+        if let (true, ControlsItem::Action(action)) = (is_delete_pressed, items[cursor.0]) {
+            let config = &mut settings.0;
+            let is_bound = !config.key_bindings.keys(action).is_empty()
+                || !config.button_bindings.inputs(action).is_empty();
+            if is_bound {
+                config.key_bindings.clear(action);
+                config.button_bindings.clear(action);
+                rebinding.message = format!("Cleared the keys and the buttons of {}", action);
+            }
+        }
+    }
+
+    draw_controls(
+        egui_ctx.ctx_mut(),
+        &layout,
+        &items,
+        &mut cursor,
+        &mut is_confirmed,
+        &rebinding,
+        &settings.0,
+        &theme,
+    );
+
+    if !is_confirmed || rebinding.capturing.is_some() {
+        return;
+    }
+    match items[cursor.0] {
+        ControlsItem::Action(action) => {
+            rebinding.capturing = Some(action);
+            rebinding.message = format!(
+                "Press a key or a button for {} (Esc to cancel or bind Esc)",
+                action
+            );
+        }
+        ControlsItem::ResetDefault => {
+            info!("Resetting key and button bindings");
+            settings.0.key_bindings = KeyBindings::default();
+            settings.0.button_bindings = ButtonBindings::default();
+            rebinding.message = String::from("Reset to the default");
+        }
+        ControlsItem::Back => back_to_settings(&mut state),
+    }
+}
+
+/// Returns to the settings screen under the controls screen.
+fn back_to_settings(state: &mut State<AppState>) {
+    if let Err(why) = state.pop() {
+        warn!("Failed to return to the settings: {:?}", why);
+    }
+}
+
+/// Binds the next pressed key or gamepad button to the action.
+fn capture_input(
+    action: Action,
+    input: &mut Input<KeyCode>,
+    buttons: &Input<GamepadButton>,
+    rebinding: &mut Rebinding,
+    config: &mut Config,
+) {
+    let just_pressed = input.get_just_pressed().next().copied();
+    if let Some(key) = just_pressed {
+        // Consume all the keys so the menu doesn't react to them.
+        input.reset_all();
+
+        // Esc is bound to an action by default, so it is bound only when pressed twice.
+        let is_escape_pending = std::mem::take(&mut rebinding.is_escape_pending);
+        if key == KeyCode::Escape && !is_escape_pending {
+            rebinding.is_escape_pending = true;
+            rebinding.message = format!(
+                "Press Esc again to bind it to {}, or another key to cancel",
+                action
+            );
+            return;
+        }
+        rebinding.capturing = None;
+        if key != KeyCode::Escape && is_escape_pending {
+            rebinding.message.clear();
+            return;
+        }
+        // Check before binding not to mark the config as changed.
+        rebinding.message = match bind_error(&config.key_bindings, action, key) {
+            Some(why) => why,
+            None => {
+                info!("Binding {:?} to {}", key, action);
+                let _ = config.key_bindings.bind(action, key);
+                format!("Bound {:?} to {}", key, action)
+            }
+        };
+    } else if let Some(button) = buttons.get_just_pressed().next() {
+        let button = button.button_type;
+        rebinding.capturing = None;
+        if std::mem::take(&mut rebinding.is_escape_pending) {
+            rebinding.message.clear();
+            return;
+        }
+        rebinding.message = match bind_error(&config.button_bindings, action, button) {
+            Some(why) => why,
+            None => {
+                info!("Binding {:?} to {}", button, action);
+                let _ = config.button_bindings.bind(action, button);
+                format!("Bound {:?} to {}", button, action)
+            }
+        };
+    }
+}
+
+/// Returns why the input can't be bound to the action.
+fn bind_error<T: Copy + PartialEq + Debug>(
+    bindings: &Bindings<T>,
+    action: Action,
+    input: T,
+) -> Option<String> {
+    match bindings.action_of(input) {
+        Some(other) if other == action => {
+            Some(format!("{:?} is already bound to {}", input, action))
+        }
+        Some(other) => Some(format!("{:?} is already used by {}", input, other)),
+        None => None,
+    }
+}
+
+/// Draws the actions and the bound keys and buttons.
+#[allow(clippy::too_many_arguments)]
+fn draw_controls(
+    ctx: &egui::Context,
+    layout: &Layout,
+    items: &[ControlsItem],
+    cursor: &mut MenuCursor,
+    is_confirmed: &mut bool,
+    rebinding: &Rebinding,
+    config: &Config,
+    theme: &Theme,
+) {
+    let window_height = layout.window.y;
+    let font_size = layout.menu_font_size * 0.7;
+    let key_conflicts = config.key_bindings.conflicts();
+    let button_conflicts = config.button_bindings.conflicts();
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui_color(theme.background)))
+        .show(ctx, |ui| {
+            ui.vertical_centered(|ui_c| {
+                ui_c.add_space(window_height * 0.05);
+                ui_c.label(egui::RichText::new("Controls").size(font_size * 2.));
+                ui_c.add_space(window_height * 0.03);
+
+                egui::Grid::new("Controls").show(ui_c, |ui_g| {
+                    for (i, item) in items.iter().enumerate() {
+                        let text = egui::RichText::new(item.label()).size(font_size);
+                        if ui_g.selectable_label(i == cursor.0, text).clicked() {
+                            cursor.0 = i;
+                            *is_confirmed = true;
+                        }
+                        if let ControlsItem::Action(action) = item {
+                            let is_capturing = rebinding.capturing == Some(*action);
+                            for (names, is_conflicted) in [
+                                (
+                                    input_names(config.key_bindings.keys(*action)),
+                                    is_conflicted(&key_conflicts, *action),
+                                ),
+                                (
+                                    input_names(config.button_bindings.inputs(*action)),
+                                    is_conflicted(&button_conflicts, *action),
+                                ),
+                            ] {
+                                let names = if is_capturing {
+                                    String::from("...")
+                                } else {
+                                    names
+                                };
+                                let mut text = egui::RichText::new(names).size(font_size);
+                                if is_conflicted {
+                                    text = text.color(egui::Color32::RED);
+                                }
+                                ui_g.label(text);
+                            }
+                        }
+                        ui_g.end_row();
+                    }
+                });
+
+                ui_c.add_space(window_height * 0.02);
+                let conflicts = key_conflicts
+                    .iter()
+                    .map(|(key, actions)| (format!("{:?}", key), actions))
+                    .chain(
+                        button_conflicts
+                            .iter()
+                            .map(|(button, actions)| (format!("{:?}", button), actions)),
+                    );
+                for (input, actions) in conflicts {
+                    let actions: Vec<_> = actions.iter().map(Action::to_string).collect();
+                    ui_c.colored_label(
+                        egui::Color32::RED,
+                        format!("{} is bound to {}", input, actions.join(", ")),
+                    );
+                }
+                ui_c.label(&rebinding.message);
+                ui_c.label("Enter: add a key or a button / Delete: clear the bindings");
+            });
+        });
+}
+
+/// Whether the action shares an input with another action.
+fn is_conflicted<T>(conflicts: &[(T, Vec<Action>)], action: Action) -> bool {
+    conflicts
+        .iter()
+        .any(|(_, actions)| actions.contains(&action))
+}
+
+/// Joins the names of the keys or the buttons.
+fn input_names<T: Debug>(inputs: &[T]) -> String {
+    if inputs.is_empty() {
+        return String::from("-");
+    }
+    inputs
+        .iter()
+        .map(|input| format!("{:?}", input))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,80 @@
+use bevy::prelude::*;
+use config_prompt::ConfigPromptPlugin;
+use controls::ControlsPlugin;
+use kgrs_core::event::ResetGameEvent;
+use kgrs_util::state::AppState;
+use mode_select::ModeSelectPlugin;
+use pause::PausePlugin;
+use profile_select::ProfileSelectPlugin;
+use results::ResultsPlugin;
+use settings::SettingsPlugin;
+use title::TitlePlugin;
+
+pub struct MenuPlugin;
+
+impl Plugin for MenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MenuCursor>()
+            .add_plugin(ConfigPromptPlugin)
+            .add_plugin(ProfileSelectPlugin)
+            .add_plugin(TitlePlugin)
+            .add_plugin(ModeSelectPlugin)
+            .add_plugin(SettingsPlugin)
+            .add_plugin(ControlsPlugin)
+            .add_plugin(PausePlugin)
+            .add_plugin(ResultsPlugin)
+            .add_system_set(SystemSet::on_enter(AppState::InGame).with_system(start_game));
+    }
+}
+
+/// Cursor of the current menu
+#[derive(Resource, Default)]
+pub(crate) struct MenuCursor(pub(crate) usize);
+
+impl MenuCursor {
+    /// Moves the cursor with the up/down keys.
+    ///
+    /// Returns whether the item at the cursor is confirmed.
+    /// The pressed keys are consumed so they don't affect the next screen.
+    pub(crate) fn navigate(&mut self, input: &mut Input<KeyCode>, len: usize) -> bool {
+        if input.clear_just_pressed(KeyCode::Up) {
+            self.0 = (self.0 + len - 1) % len;
+        }
+        if input.clear_just_pressed(KeyCode::Down) {
+            self.0 = (self.0 + 1) % len;
+        }
+        // Don't short-circuit to consume both keys.
+        input.clear_just_pressed(KeyCode::Return) | input.clear_just_pressed(KeyCode::Space)
+    }
+}
+
+/// Whether the back key is pressed. The key is consumed.
+pub(crate) fn is_back_pressed(input: &mut Input<KeyCode>) -> bool {
+    input.clear_just_pressed(KeyCode::Escape)
+}
+
+/// Changes the app state to `next` replacing the whole state stack.
+pub(crate) fn go_to(state: &mut State<AppState>, next: AppState) {
+    if let Err(why) = state.replace(next) {
+        warn!("Failed to change the state to {:?}: {:?}", next, why);
+    }
+}
+
+/// Moves the cursor to the first item.
+fn reset_cursor(mut cursor: ResMut<MenuCursor>) {
+    cursor.0 = 0;
+}
+
+/// Starts a new game.
+fn start_game(mut reset_writer: EventWriter<ResetGameEvent>) {
+    reset_writer.send(ResetGameEvent { keep_seed: false });
+}
+
+pub(crate) mod config_prompt;
+pub(crate) mod controls;
+pub(crate) mod mode_select;
+pub(crate) mod pause;
+pub(crate) mod profile_select;
+pub(crate) mod results;
+pub(crate) mod settings;
+pub(crate) mod title;
@@ -0,0 +1,68 @@
+use super::*;
+use crate::style::*;
+use bevy_egui::{egui, EguiContext};
+use kgrs_core::{layout::Layout, mode::GameMode, theme::Theme};
+
+pub(crate) struct ModeSelectPlugin;
+
+impl Plugin for ModeSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::ModeSelect).with_system(reset_cursor))
+            .add_system_set(SystemSet::on_update(AppState::ModeSelect).with_system(mode_select));
+    }
+}
+
+/// Items of the mode select menu.
+/// `None` is the back button.
+const MODE_ITEMS: [Option<GameMode>; 3] = [Some(GameMode::Free), Some(GameMode::PcPractice), None];
+
+/// Mode select screen
+fn mode_select(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut game_mode: ResMut<GameMode>,
+    theme: Res<Theme>,
+) {
+    let window_height = layout.window.y;
+    let mut is_confirmed = cursor.navigate(&mut input, MODE_ITEMS.len());
+    if is_back_pressed(&mut input) {
+        go_to(&mut state, AppState::Title);
+        return;
+    }
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui_color(theme.background)))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui_c| {
+                let font_size = layout.menu_font_size;
+                ui_c.add_space(window_height * 0.2);
+                ui_c.label(egui::RichText::new("Select Mode").size(font_size * 1.5));
+                ui_c.add_space(window_height * 0.05);
+
+                let labels = MODE_ITEMS.map(|item| match item {
+                    Some(mode) => mode.to_string(),
+                    None => String::from("Back"),
+                });
+                if let Some(i) = menu_items(ui_c, &labels, cursor.0, font_size) {
+                    cursor.0 = i;
+                    is_confirmed = true;
+                }
+            });
+        });
+
+    if is_confirmed {
+        match MODE_ITEMS[cursor.0] {
+            Some(mode) => {
+                if *game_mode != mode {
+                    *game_mode = mode;
+                }
+                info!("Starting {}", mode);
+                go_to(&mut state, AppState::InGame);
+            }
+            None => go_to(&mut state, AppState::Title),
+        }
+    }
+}
@@ -0,0 +1,92 @@
+use super::*;
+use crate::style::*;
+use bevy_egui::{egui, EguiContext};
+use kgrs_config::binding::Action;
+use kgrs_core::{event::GameOverEvent, input::ActionsMut, layout::Layout};
+
+pub(crate) struct PausePlugin;
+
+impl Plugin for PausePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_update(AppState::InGame).with_system(pause_game))
+            .add_system_set(SystemSet::on_enter(AppState::Paused).with_system(reset_cursor))
+            .add_system_set(SystemSet::on_update(AppState::Paused).with_system(pause_menu));
+    }
+}
+
+/// Items of the pause menu
+#[derive(Clone, Copy)]
+enum PauseItem {
+    Resume,
+    Restart,
+    EndGame,
+}
+
+impl PauseItem {
+    const ALL: [Self; 3] = [Self::Resume, Self::Restart, Self::EndGame];
+
+    fn label(&self) -> String {
+        match self {
+            Self::Resume => "Resume",
+            Self::Restart => "Restart",
+            Self::EndGame => "End Game",
+        }
+        .to_string()
+    }
+}
+
+/// Pauses the game by pushing `AppState::Paused`.
+/// The systems in `AppState::InGame` and the input clock are frozen while paused.
+fn pause_game(mut actions: ActionsMut, mut state: ResMut<State<AppState>>) {
+    if actions.clear_just_pressed(Action::Pause) {
+        info!("Paused");
+        if let Err(why) = state.push(AppState::Paused) {
+            warn!("Failed to pause: {:?}", why);
+        }
+    }
+}
+
+/// Pause menu shown over the board
+fn pause_menu(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut reset_writer: EventWriter<ResetGameEvent>,
+    mut game_over_writer: EventWriter<GameOverEvent>,
+) {
+    let font_size = layout.menu_font_size;
+    let mut is_confirmed = cursor.navigate(&mut input, PauseItem::ALL.len());
+    if is_back_pressed(&mut input) {
+        cursor.0 = 0;
+        is_confirmed = true;
+    }
+
+    egui::Window::new("Paused")
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let labels = PauseItem::ALL.map(|item| item.label());
+            if let Some(i) = menu_items(ui, &labels, cursor.0, font_size) {
+                cursor.0 = i;
+                is_confirmed = true;
+            }
+        });
+
+    if is_confirmed {
+        match PauseItem::ALL[cursor.0] {
+            PauseItem::Resume => info!("Resumed"),
+            PauseItem::Restart => reset_writer.send(ResetGameEvent { keep_seed: false }),
+            // The results screen is shown by `GameOverEvent`.
+            PauseItem::EndGame => {
+                game_over_writer.send(GameOverEvent { top_out: false });
+                return;
+            }
+        }
+        if let Err(why) = state.pop() {
+            warn!("Failed to resume: {:?}", why);
+        }
+    }
+}
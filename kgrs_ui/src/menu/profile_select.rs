@@ -0,0 +1,215 @@
+use super::*;
+use crate::style::*;
+use bevy_egui::{egui, EguiContext};
+use kgrs_config::{
+    profile::{self, ActiveProfile, Profile},
+    Config,
+};
+use kgrs_core::{layout::Layout, theme::Theme};
+use std::path::Path;
+
+pub(crate) struct ProfileSelectPlugin;
+
+impl Plugin for ProfileSelectPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ProfileForm>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::ProfileSelect)
+                    .with_system(reset_cursor)
+                    .with_system(list_profiles),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::ProfileSelect).with_system(profile_select),
+            );
+    }
+}
+
+/// State of the profile select screen
+#[derive(Resource, Default)]
+struct ProfileForm {
+    /// Names of the saved profiles.
+    names: Vec<String>,
+    /// Name of the profile to create.
+    new_name: String,
+    /// Path of the file to import.
+    import_path: String,
+    /// Result of the last operation.
+    message: String,
+}
+
+/// Items of the profile select menu
+#[derive(Clone, PartialEq, Eq)]
+enum ProfileItem {
+    Profile(String),
+    New,
+    Import,
+    /// Shown only when a profile is selected.
+    Export,
+    Guest,
+}
+
+impl ProfileItem {
+    fn label(&self) -> String {
+        match self {
+            Self::Profile(name) => name.clone(),
+            Self::New => String::from("New Profile"),
+            Self::Import => String::from("Import"),
+            Self::Export => String::from("Export Current Profile"),
+            Self::Guest => String::from("Play as Guest"),
+        }
+    }
+}
+
+/// Reads the names of the saved profiles.
+fn list_profiles(mut form: ResMut<ProfileForm>) {
+    form.names = Profile::list();
+    form.message.clear();
+}
+
+/// Profile select screen
+#[allow(clippy::too_many_arguments)]
+fn profile_select(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut form: ResMut<ProfileForm>,
+    mut active: ResMut<ActiveProfile>,
+    mut config: ResMut<Config>,
+    theme: Res<Theme>,
+) {
+    let window_height = layout.window.y;
+    let font_size = layout.menu_font_size;
+
+    let mut items: Vec<_> = form
+        .names
+        .iter()
+        .cloned()
+        .map(ProfileItem::Profile)
+        .collect();
+    items.push(ProfileItem::New);
+    items.push(ProfileItem::Import);
+    if active.0.is_some() {
+        items.push(ProfileItem::Export);
+    }
+    items.push(ProfileItem::Guest);
+
+    cursor.0 = cursor.0.min(items.len() - 1);
+    if matches!(items[cursor.0], ProfileItem::New | ProfileItem::Import) {
+        // Space is typed in the text field instead of confirming.
+        input.clear_just_pressed(KeyCode::Space);
+    }
+    let mut is_confirmed = cursor.navigate(&mut input, items.len());
+    if is_back_pressed(&mut input) {
+        go_to(&mut state, AppState::Title);
+        return;
+    }
+
+    let form = &mut *form;
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui_color(theme.background)))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui_c| {
+                ui_c.add_space(window_height * 0.1);
+                ui_c.label(egui::RichText::new("Select Profile").size(font_size * 1.5));
+                if let Some(profile) = &active.0 {
+                    ui_c.label(format!("Current: {}", profile.name));
+                }
+                ui_c.add_space(window_height * 0.05);
+
+                egui::Grid::new("Profiles").show(ui_c, |ui_g| {
+                    for (i, item) in items.iter().enumerate() {
+                        let text = egui::RichText::new(item.label()).size(font_size);
+                        if ui_g.selectable_label(i == cursor.0, text).clicked() {
+                            cursor.0 = i;
+                            is_confirmed = true;
+                        }
+                        let field = match item {
+                            ProfileItem::New => Some(&mut form.new_name),
+                            ProfileItem::Import => Some(&mut form.import_path),
+                            _ => None,
+                        };
+                        if let Some(field) = field {
+                            let response = ui_g.text_edit_singleline(field);
+                            if i == cursor.0 && !response.has_focus() {
+                                response.request_focus();
+                            }
+                        }
+                        ui_g.end_row();
+                    }
+                });
+
+                ui_c.add_space(window_height * 0.03);
+                ui_c.label(&form.message);
+            });
+        });
+
+    if !is_confirmed {
+        return;
+    }
+    match &items[cursor.0] {
+        ProfileItem::Profile(name) => match Profile::load(name) {
+            Ok(profile) => {
+                select(profile, &mut active, &mut config);
+                go_to(&mut state, AppState::Title);
+            }
+            Err(why) => form.message = format!("Failed to load {}: {}", name, why),
+        },
+        ProfileItem::New => {
+            let name = form.new_name.trim().to_string();
+            if let Err(why) = profile::validate_name(&name) {
+                form.message = why;
+            } else if form.names.contains(&name) {
+                form.message = format!("{} already exists", name);
+            } else {
+                info!("Creating profile {}", name);
+                let profile = Profile::new(&name, &config);
+                profile.save();
+                form.new_name.clear();
+                select(profile, &mut active, &mut config);
+                go_to(&mut state, AppState::Title);
+            }
+        }
+        ProfileItem::Import => match Profile::import(Path::new(form.import_path.trim())) {
+            Ok(profile) if form.names.contains(&profile.name) => {
+                form.message = format!("{} already exists", profile.name);
+            }
+            Ok(profile) => {
+                info!("Importing profile {}", profile.name);
+                profile.save();
+                form.message = format!("Imported {}", profile.name);
+                form.names = Profile::list();
+            }
+            Err(why) => form.message = format!("Failed to import: {}", why),
+        },
+        ProfileItem::Export => {
+            if let Some(profile) = &active.0 {
+                let file = profile::export_file(&profile.name);
+                form.message = match profile.export(&file) {
+                    Ok(()) => format!("Exported to {}", file.display()),
+                    Err(why) => format!("Failed to export: {}", why),
+                };
+            }
+        }
+        ProfileItem::Guest => {
+            if let Some(profile) = active.0.take() {
+                profile.save();
+            }
+            go_to(&mut state, AppState::Title);
+        }
+    }
+}
+
+/// Switches to the profile and applies its settings.
+fn select(profile: Profile, active: &mut ActiveProfile, config: &mut Config) {
+    info!("Selecting profile {}", profile.name);
+    // Save the unsaved changes of the previous profile.
+    if let Some(previous) = &active.0 {
+        previous.save();
+    }
+    if !profile.is_applied_to(config) {
+        profile.apply_to(config);
+    }
+    active.0 = Some(profile);
+}
@@ -0,0 +1,98 @@
+use super::*;
+use crate::style::*;
+use bevy_egui::{egui, EguiContext};
+use kgrs_core::{
+    event::GameOverEvent, layout::Layout, mode::GameMode, score::Score, stats::GameStats,
+};
+
+pub(crate) struct ResultsPlugin;
+
+impl Plugin for ResultsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(show_results_on_game_over)
+            .add_system_set(SystemSet::on_enter(AppState::Results).with_system(reset_cursor))
+            .add_system_set(SystemSet::on_update(AppState::Results).with_system(results));
+    }
+}
+
+/// Items of the results menu
+#[derive(Clone, Copy)]
+enum ResultsItem {
+    Retry,
+    Menu,
+}
+
+impl ResultsItem {
+    const ALL: [Self; 2] = [Self::Retry, Self::Menu];
+
+    fn label(&self) -> String {
+        match self {
+            Self::Retry => "Retry",
+            Self::Menu => "Back to Menu",
+        }
+        .to_string()
+    }
+}
+
+/// Shows the results screen when the game is over.
+fn show_results_on_game_over(
+    mut game_over_reader: EventReader<GameOverEvent>,
+    mut state: ResMut<State<AppState>>,
+) {
+    if game_over_reader.iter().next().is_some() {
+        go_to(&mut state, AppState::Results);
+    }
+}
+
+/// Results screen of the finished game
+#[allow(clippy::too_many_arguments)]
+fn results(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mode: Res<GameMode>,
+    score: Res<Score>,
+    stats: Res<GameStats>,
+) {
+    let font_size = layout.menu_font_size;
+    let mut is_confirmed = cursor.navigate(&mut input, ResultsItem::ALL.len());
+    if is_back_pressed(&mut input) {
+        go_to(&mut state, AppState::Title);
+        return;
+    }
+
+    egui::Window::new(format!("Results - {}", *mode))
+        .anchor(egui::Align2::CENTER_CENTER, [0., 0.])
+        .collapsible(false)
+        .resizable(false)
+        .show(egui_ctx.ctx_mut(), |ui| {
+            let secs = stats.time.elapsed_secs();
+            for (name, value) in [
+                ("Score", score.points.to_string()),
+                ("Lines", score.lines.to_string()),
+                ("Level", score.level().to_string()),
+                ("Time", format_time(secs)),
+                ("PPS", format!("{:.2}", stats.pps())),
+                ("APM", format!("{:.1}", stats.apm(score.attack))),
+            ] {
+                ui.label(egui::RichText::new(format!("{}: {}", name, value)).size(font_size * 0.7));
+            }
+            ui.separator();
+
+            let labels = ResultsItem::ALL.map(|item| item.label());
+            if let Some(i) = menu_items(ui, &labels, cursor.0, font_size) {
+                cursor.0 = i;
+                is_confirmed = true;
+            }
+        });
+
+    if is_confirmed {
+        match ResultsItem::ALL[cursor.0] {
+            // A new game is started when entering `AppState::InGame`.
+            ResultsItem::Retry => go_to(&mut state, AppState::InGame),
+            ResultsItem::Menu => go_to(&mut state, AppState::Title),
+        }
+    }
+}
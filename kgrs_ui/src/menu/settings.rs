@@ -0,0 +1,702 @@
+use super::*;
+use crate::style::*;
+use bevy::{audio::AudioSink, window::PresentMode};
+use bevy_egui::{egui, EguiContext};
+use kgrs_audio::{music::MusicPlayer, sfx::SfxPackList};
+use kgrs_config::{
+    BoardSize, BothHeld, ColorBlind, Config, ConfigPreview, Repeat, WindowModeForConf,
+};
+use kgrs_core::{
+    board::{set_grid_opacity, Grid},
+    layout::Layout,
+    theme::{Theme, ThemeList},
+};
+use kgrs_util::function::fmt::wm_to_string;
+use std::{num::NonZeroU8, ops::RangeInclusive};
+
+pub(crate) struct SettingsPlugin;
+
+impl Plugin for SettingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<SettingsDraft>()
+            .init_resource::<ConfigPreview>()
+            .add_system_set(
+                SystemSet::on_enter(AppState::Settings)
+                    .with_system(reset_cursor)
+                    .with_system(load_settings),
+            )
+            .add_system_set(
+                SystemSet::on_update(AppState::Settings)
+                    .with_system(settings)
+                    .with_system(preview_settings),
+            )
+            .add_system_set(
+                SystemSet::on_resume(AppState::Settings).with_system(select_controls_item),
+            )
+            .add_system_set(SystemSet::on_exit(AppState::Settings).with_system(discard_settings));
+    }
+}
+
+/// Config being edited and previewed in the settings screen
+///
+/// The controls screen is pushed on top of the settings screen and edits the bindings of it,
+/// so they are applied and reverted together with the other settings.
+#[derive(Resource, Default)]
+pub(crate) struct SettingsDraft(pub(crate) Config);
+
+/// Items of the settings menu
+#[derive(Clone, Copy)]
+enum SettingsItem {
+    Vsync,
+    WindowMode,
+    GridOpacity,
+    UiScale,
+    Theme,
+    ColorBlind,
+    MinoGlyphs,
+    ReducedMotion,
+    LineClearFlash,
+    Collapse,
+    HardDropTrail,
+    LockFlash,
+    BoardShake,
+    Particles,
+    MusicVolume,
+    MusicShuffle,
+    MusicRepeat,
+    AdaptiveMusic,
+    DangerHeight,
+    SfxVolume,
+    SfxPack,
+    BoardWidth,
+    BoardHeight,
+    BoardBuffer,
+    Das,
+    Arr,
+    Sdf,
+    Dcd,
+    DasCarry,
+    BothHeld,
+    Irs,
+    Ihs,
+    InputRate,
+    StickDeadzone,
+    Controls,
+    Apply,
+    Revert,
+    Back,
+}
+
+impl SettingsItem {
+    const ALL: [Self; 38] = [
+        Self::Vsync,
+        Self::WindowMode,
+        Self::GridOpacity,
+        Self::UiScale,
+        Self::Theme,
+        Self::ColorBlind,
+        Self::MinoGlyphs,
+        Self::ReducedMotion,
+        Self::LineClearFlash,
+        Self::Collapse,
+        Self::HardDropTrail,
+        Self::LockFlash,
+        Self::BoardShake,
+        Self::Particles,
+        Self::MusicVolume,
+        Self::MusicShuffle,
+        Self::MusicRepeat,
+        Self::AdaptiveMusic,
+        Self::DangerHeight,
+        Self::SfxVolume,
+        Self::SfxPack,
+        Self::BoardWidth,
+        Self::BoardHeight,
+        Self::BoardBuffer,
+        Self::Das,
+        Self::Arr,
+        Self::Sdf,
+        Self::Dcd,
+        Self::DasCarry,
+        Self::BothHeld,
+        Self::Irs,
+        Self::Ihs,
+        Self::InputRate,
+        Self::StickDeadzone,
+        Self::Controls,
+        Self::Apply,
+        Self::Revert,
+        Self::Back,
+    ];
+
+    fn label(&self) -> &'static str {
+        match self {
+            Self::Vsync => "VSync",
+            Self::WindowMode => "Window Mode",
+            Self::GridOpacity => "Grid Opacity",
+            Self::UiScale => "UI Scale",
+            Self::Theme => "Theme",
+            Self::ColorBlind => "Colour-blind Mode",
+            Self::MinoGlyphs => "Mino Glyphs",
+            Self::ReducedMotion => "Reduced Motion",
+            Self::LineClearFlash => "Line Clear Flash",
+            Self::Collapse => "Collapse Animation",
+            Self::HardDropTrail => "Hard Drop Trail",
+            Self::LockFlash => "Lock Flash",
+            Self::BoardShake => "Board Shake",
+            Self::Particles => "Particles",
+            Self::MusicVolume => "Music Volume",
+            Self::MusicShuffle => "Shuffle Music",
+            Self::MusicRepeat => "Repeat Music",
+            Self::AdaptiveMusic => "Adaptive Music",
+            Self::DangerHeight => "Danger Height",
+            Self::SfxVolume => "SFX Volume",
+            Self::SfxPack => "SFX Pack",
+            Self::BoardWidth => "Board Width",
+            Self::BoardHeight => "Board Height",
+            Self::BoardBuffer => "Buffer Height",
+            Self::Das => "DAS (Frame)",
+            Self::Arr => "ARR (Frame)",
+            Self::Sdf => "Soft Drop Factor",
+            Self::Dcd => "DAS Cut Delay (Frame)",
+            Self::DasCarry => "DAS Carry",
+            Self::BothHeld => "Left + Right",
+            Self::Irs => "IRS",
+            Self::Ihs => "IHS",
+            Self::InputRate => "Input Rate",
+            Self::StickDeadzone => "Stick Deadzone",
+            Self::Controls => "Controls",
+            Self::Apply => "Apply",
+            Self::Revert => "Revert",
+            Self::Back => "Back",
+        }
+    }
+
+    /// Changes the value of the item by `delta` steps with the left/right keys.
+    ///
+    /// The theme and the SFX pack are chosen from `choices`.
+    fn adjust(&self, config: &mut Config, delta: i32, choices: &Choices) {
+        match self {
+            Self::Vsync => config.vsync = !config.vsync,
+            Self::WindowMode => {
+                let i = WINDOW_MODES
+                    .iter()
+                    .position(|wm| *wm == config.window_mode)
+                    .unwrap_or_default() as i32;
+                let len = WINDOW_MODES.len() as i32;
+                config.window_mode = WINDOW_MODES[(i + delta).rem_euclid(len) as usize];
+            }
+            Self::GridOpacity => {
+                config.grid_opacity = (config.grid_opacity as i32 + delta).clamp(0, 100) as u8;
+            }
+            Self::UiScale => {
+                config.ui_scale = (config.ui_scale as i32 + delta * 5).clamp(50, 150) as u8;
+            }
+            Self::Theme => cycle_name(&mut config.theme, choices.themes, delta),
+            Self::ColorBlind => {
+                let i = ColorBlind::ALL
+                    .iter()
+                    .position(|c| *c == config.color_blind)
+                    .unwrap_or_default() as i32;
+                let len = ColorBlind::ALL.len() as i32;
+                config.color_blind = ColorBlind::ALL[(i + delta).rem_euclid(len) as usize];
+            }
+            Self::MinoGlyphs => config.mino_glyphs = !config.mino_glyphs,
+            Self::ReducedMotion => config.effects.reduced_motion = !config.effects.reduced_motion,
+            Self::LineClearFlash => {
+                config.effects.line_clear_flash = !config.effects.line_clear_flash
+            }
+            Self::Collapse => config.effects.collapse = !config.effects.collapse,
+            Self::HardDropTrail => config.effects.hard_drop_trail = !config.effects.hard_drop_trail,
+            Self::LockFlash => config.effects.lock_flash = !config.effects.lock_flash,
+            Self::BoardShake => config.effects.board_shake = !config.effects.board_shake,
+            Self::Particles => config.effects.particles = !config.effects.particles,
+            Self::MusicVolume => {
+                config.music_volume = (config.music_volume + delta as f32).clamp(0., 100.);
+            }
+            Self::MusicShuffle => config.music_shuffle = !config.music_shuffle,
+            Self::MusicRepeat => {
+                let i = Repeat::ALL
+                    .iter()
+                    .position(|r| *r == config.music_repeat)
+                    .unwrap_or_default() as i32;
+                let len = Repeat::ALL.len() as i32;
+                config.music_repeat = Repeat::ALL[(i + delta).rem_euclid(len) as usize];
+            }
+            Self::AdaptiveMusic => config.adaptive_music = !config.adaptive_music,
+            Self::DangerHeight => {
+                config.danger_ratio = (config.danger_ratio as i32 + delta).clamp(1, 100) as u8;
+            }
+            Self::SfxVolume => {
+                config.sfx_volume = (config.sfx_volume + delta as f32).clamp(0., 100.);
+            }
+            Self::SfxPack => cycle_name(&mut config.sfx_pack, choices.sfx_packs, delta),
+            Self::BoardWidth => {
+                config.board.width = step_in(config.board.width, delta, BoardSize::WIDTH_RANGE);
+            }
+            Self::BoardHeight => {
+                config.board.height = step_in(config.board.height, delta, BoardSize::HEIGHT_RANGE);
+            }
+            Self::BoardBuffer => {
+                config.board.buffer = step_in(config.board.buffer, delta, BoardSize::BUFFER_RANGE);
+            }
+            Self::Das => {
+                let das = (config.handling.das.get() as i32 + delta).clamp(1, 255) as u8;
+                if let Some(das) = NonZeroU8::new(das) {
+                    config.handling.das = das;
+                }
+            }
+            Self::Arr => {
+                // ARR can be fractional so it's changed by 0.1 frame.
+                let arr = config.handling.arr + delta as f32 * 0.1;
+                config.handling.arr = ((arr * 10.).round() / 10.).clamp(0., 255.);
+            }
+            Self::Sdf => {
+                let sdf = (config.handling.sdf.get() as i32 + delta).clamp(1, 255) as u8;
+                if let Some(sdf) = NonZeroU8::new(sdf) {
+                    config.handling.sdf = sdf;
+                }
+            }
+            Self::Dcd => {
+                config.handling.dcd = (config.handling.dcd as i32 + delta).clamp(0, 255) as u8;
+            }
+            Self::DasCarry => config.handling.das_carry = !config.handling.das_carry,
+            Self::BothHeld => {
+                let i = BothHeld::ALL
+                    .iter()
+                    .position(|b| *b == config.handling.both_held)
+                    .unwrap_or_default() as i32;
+                let len = BothHeld::ALL.len() as i32;
+                config.handling.both_held = BothHeld::ALL[(i + delta).rem_euclid(len) as usize];
+            }
+            Self::Irs => config.handling.irs = !config.handling.irs,
+            Self::Ihs => config.handling.ihs = !config.handling.ihs,
+            Self::InputRate => {
+                let rate = config.handling.input_rate as i32 + delta * 10;
+                config.handling.input_rate = rate.clamp(60, 1000) as u16;
+            }
+            Self::StickDeadzone => {
+                config.stick_deadzone = (config.stick_deadzone as i32 + delta).clamp(0, 90) as u8;
+            }
+            Self::Controls | Self::Apply | Self::Revert | Self::Back => {}
+        }
+    }
+}
+
+/// Names of the themes and the SFX packs that can be chosen
+struct Choices<'a> {
+    themes: &'a [String],
+    sfx_packs: &'a [String],
+}
+
+/// Switches `current` to the name `delta` steps away in `names`.
+fn cycle_name(current: &mut String, names: &[String], delta: i32) {
+    let i = names
+        .iter()
+        .position(|name| name == current)
+        .unwrap_or_default() as i32;
+    let len = names.len() as i32;
+    if let Some(name) = names.get((i + delta).rem_euclid(len.max(1)) as usize) {
+        *current = name.clone();
+    }
+}
+
+/// Changes the value by `delta` within the range.
+fn step_in(value: u8, delta: i32, range: RangeInclusive<u8>) -> u8 {
+    (value as i32 + delta).clamp(*range.start() as i32, *range.end() as i32) as u8
+}
+
+/// Window modes in the order of switching
+const WINDOW_MODES: [WindowModeForConf; 4] = [
+    WindowModeForConf::Windowed,
+    WindowModeForConf::BorderlessFullscreen,
+    WindowModeForConf::Fullscreen,
+    WindowModeForConf::SizedFullscreen,
+];
+
+/// Puts the cursor back on the controls item when returning from the controls screen.
+fn select_controls_item(mut cursor: ResMut<MenuCursor>) {
+    if let Some(i) = SettingsItem::ALL
+        .iter()
+        .position(|item| matches!(item, SettingsItem::Controls))
+    {
+        cursor.0 = i;
+    }
+}
+
+/// Copies the current config to edit.
+fn load_settings(config: Res<Config>, mut settings: ResMut<SettingsDraft>) {
+    settings.0 = config.clone();
+}
+
+/// Settings screen
+#[allow(clippy::too_many_arguments)]
+fn settings(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut settings: ResMut<SettingsDraft>,
+    mut config: ResMut<Config>,
+    themes: Res<ThemeList>,
+    sfx_packs: Res<SfxPackList>,
+    theme: Res<Theme>,
+) {
+    let choices = Choices {
+        themes: &themes.0,
+        sfx_packs: &sfx_packs.0,
+    };
+    let window_height = layout.window.y;
+    let font_size = layout.menu_font_size;
+    // Edit a copy so as not to trigger the preview when nothing is changed.
+    let mut draft = settings.0.clone();
+
+    let mut is_confirmed = cursor.navigate(&mut input, SettingsItem::ALL.len());
+    let item = SettingsItem::ALL[cursor.0];
+    if input.clear_just_pressed(KeyCode::Left) {
+        item.adjust(&mut draft, -1, &choices);
+    }
+    if input.clear_just_pressed(KeyCode::Right) {
+        item.adjust(&mut draft, 1, &choices);
+    }
+    if is_back_pressed(&mut input) {
+        go_to(&mut state, AppState::Title);
+        return;
+    }
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui_color(theme.background)))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui_c| {
+                ui_c.add_space(window_height * 0.1);
+                ui_c.label(egui::RichText::new("Settings").size(font_size * 1.5));
+                ui_c.add_space(window_height * 0.05);
+
+                egui::Grid::new("Settings").show(ui_c, |ui_g| {
+                    for (i, item) in SettingsItem::ALL.iter().enumerate() {
+                        let text = egui::RichText::new(item.label()).size(font_size);
+                        if ui_g.selectable_label(i == cursor.0, text).clicked() {
+                            cursor.0 = i;
+                            is_confirmed = true;
+                        }
+                        settings_value(ui_g, item, &mut draft, &choices);
+                        ui_g.end_row();
+                    }
+                });
+            });
+        });
+
+    if is_confirmed {
+        match SettingsItem::ALL[cursor.0] {
+            item @ (SettingsItem::Vsync
+            | SettingsItem::WindowMode
+            | SettingsItem::Theme
+            | SettingsItem::ColorBlind
+            | SettingsItem::MinoGlyphs
+            | SettingsItem::ReducedMotion
+            | SettingsItem::LineClearFlash
+            | SettingsItem::Collapse
+            | SettingsItem::HardDropTrail
+            | SettingsItem::LockFlash
+            | SettingsItem::BoardShake
+            | SettingsItem::Particles
+            | SettingsItem::MusicShuffle
+            | SettingsItem::MusicRepeat
+            | SettingsItem::AdaptiveMusic
+            | SettingsItem::SfxPack
+            | SettingsItem::DasCarry
+            | SettingsItem::BothHeld
+            | SettingsItem::Irs
+            | SettingsItem::Ihs) => item.adjust(&mut draft, 1, &choices),
+            SettingsItem::Apply => {
+                info!("Applying settings");
+                *config = draft.clone();
+            }
+            SettingsItem::Revert => {
+                info!("Reverting settings");
+                draft = config.clone();
+            }
+            SettingsItem::Controls => {
+                // Push the controls screen to keep the draft while rebinding.
+                if let Err(why) = state.push(AppState::Controls) {
+                    warn!("Failed to open the controls: {:?}", why);
+                }
+            }
+            SettingsItem::Back => go_to(&mut state, AppState::Title),
+            _ => {}
+        }
+    }
+
+    if draft != settings.0 {
+        settings.0 = draft;
+    }
+}
+
+/// Draws the widget to edit the value of the item.
+fn settings_value(ui: &mut egui::Ui, item: &SettingsItem, config: &mut Config, choices: &Choices) {
+    match item {
+        SettingsItem::Vsync => {
+            ui.checkbox(&mut config.vsync, "");
+        }
+        SettingsItem::WindowMode => {
+            egui::ComboBox::from_id_source("SettingsWindowMode")
+                .selected_text(wm_to_string(config.window_mode.to_window_mode()))
+                .show_ui(ui, |ui_wm| {
+                    for wm in WINDOW_MODES {
+                        ui_wm.selectable_value(
+                            &mut config.window_mode,
+                            wm,
+                            wm_to_string(wm.to_window_mode()),
+                        );
+                    }
+                });
+        }
+        SettingsItem::GridOpacity => {
+            ui.add(egui::Slider::new(&mut config.grid_opacity, 0..=100).suffix("%"));
+        }
+        SettingsItem::UiScale => {
+            ui.add(egui::Slider::new(&mut config.ui_scale, 50..=150).suffix("%"));
+        }
+        SettingsItem::Theme => {
+            egui::ComboBox::from_id_source("SettingsTheme")
+                .selected_text(&config.theme)
+                .show_ui(ui, |ui_t| {
+                    for theme in choices.themes {
+                        ui_t.selectable_value(&mut config.theme, theme.clone(), theme);
+                    }
+                });
+        }
+        SettingsItem::ColorBlind => {
+            egui::ComboBox::from_id_source("SettingsColorBlind")
+                .selected_text(config.color_blind.to_string())
+                .show_ui(ui, |ui_c| {
+                    for color_blind in ColorBlind::ALL {
+                        ui_c.selectable_value(
+                            &mut config.color_blind,
+                            color_blind,
+                            color_blind.to_string(),
+                        );
+                    }
+                });
+        }
+        SettingsItem::MinoGlyphs => {
+            ui.checkbox(&mut config.mino_glyphs, "");
+        }
+        SettingsItem::ReducedMotion => {
+            ui.checkbox(&mut config.effects.reduced_motion, "");
+        }
+        SettingsItem::LineClearFlash => {
+            ui.checkbox(&mut config.effects.line_clear_flash, "");
+        }
+        SettingsItem::Collapse => {
+            ui.checkbox(&mut config.effects.collapse, "");
+        }
+        SettingsItem::HardDropTrail => {
+            ui.checkbox(&mut config.effects.hard_drop_trail, "");
+        }
+        SettingsItem::LockFlash => {
+            ui.checkbox(&mut config.effects.lock_flash, "");
+        }
+        SettingsItem::BoardShake => {
+            ui.checkbox(&mut config.effects.board_shake, "");
+        }
+        SettingsItem::Particles => {
+            ui.checkbox(&mut config.effects.particles, "");
+        }
+        SettingsItem::MusicVolume => {
+            ui.add(egui::Slider::new(&mut config.music_volume, 0.0..=100.0).suffix("%"));
+        }
+        SettingsItem::MusicShuffle => {
+            ui.checkbox(&mut config.music_shuffle, "");
+        }
+        SettingsItem::MusicRepeat => {
+            egui::ComboBox::from_id_source("SettingsMusicRepeat")
+                .selected_text(config.music_repeat.to_string())
+                .show_ui(ui, |ui_r| {
+                    for repeat in Repeat::ALL {
+                        ui_r.selectable_value(&mut config.music_repeat, repeat, repeat.to_string());
+                    }
+                });
+        }
+        SettingsItem::AdaptiveMusic => {
+            ui.checkbox(&mut config.adaptive_music, "");
+        }
+        SettingsItem::DangerHeight => {
+            ui.add(egui::Slider::new(&mut config.danger_ratio, 1..=100).suffix("%"));
+        }
+        SettingsItem::SfxVolume => {
+            ui.add(egui::Slider::new(&mut config.sfx_volume, 0.0..=100.0).suffix("%"));
+        }
+        SettingsItem::SfxPack => {
+            egui::ComboBox::from_id_source("SettingsSfxPack")
+                .selected_text(&config.sfx_pack)
+                .show_ui(ui, |ui_s| {
+                    for pack in choices.sfx_packs {
+                        ui_s.selectable_value(&mut config.sfx_pack, pack.clone(), pack);
+                    }
+                });
+        }
+        SettingsItem::BoardWidth => {
+            ui.add(egui::Slider::new(
+                &mut config.board.width,
+                BoardSize::WIDTH_RANGE,
+            ));
+        }
+        SettingsItem::BoardHeight => {
+            ui.add(egui::Slider::new(
+                &mut config.board.height,
+                BoardSize::HEIGHT_RANGE,
+            ));
+        }
+        SettingsItem::BoardBuffer => {
+            ui.add(egui::Slider::new(
+                &mut config.board.buffer,
+                BoardSize::BUFFER_RANGE,
+            ));
+        }
+        SettingsItem::Das => {
+            let mut das = config.handling.das.get();
+            ui.add(egui::Slider::new(&mut das, 1..=60).clamp_to_range(false));
+            if let Some(das) = NonZeroU8::new(das) {
+                config.handling.das = das;
+            }
+        }
+        SettingsItem::Arr => {
+            ui.add(
+                egui::Slider::new(&mut config.handling.arr, 0.0..=60.0)
+                    .step_by(0.1)
+                    .clamp_to_range(false),
+            );
+        }
+        SettingsItem::Sdf => {
+            let mut sdf = config.handling.sdf.get();
+            ui.add(egui::Slider::new(&mut sdf, 1..=40).clamp_to_range(false));
+            if let Some(sdf) = NonZeroU8::new(sdf) {
+                config.handling.sdf = sdf;
+            }
+        }
+        SettingsItem::Dcd => {
+            ui.add(egui::Slider::new(&mut config.handling.dcd, 0..=20).clamp_to_range(false));
+        }
+        SettingsItem::DasCarry => {
+            ui.checkbox(&mut config.handling.das_carry, "");
+        }
+        SettingsItem::BothHeld => {
+            egui::ComboBox::from_id_source("SettingsBothHeld")
+                .selected_text(config.handling.both_held.to_string())
+                .show_ui(ui, |ui_b| {
+                    for both_held in BothHeld::ALL {
+                        ui_b.selectable_value(
+                            &mut config.handling.both_held,
+                            both_held,
+                            both_held.to_string(),
+                        );
+                    }
+                });
+        }
+        SettingsItem::Irs => {
+            ui.checkbox(&mut config.handling.irs, "");
+        }
+        SettingsItem::Ihs => {
+            ui.checkbox(&mut config.handling.ihs, "");
+        }
+        SettingsItem::InputRate => {
+            ui.add(egui::Slider::new(&mut config.handling.input_rate, 60..=1000).suffix("Hz"));
+        }
+        SettingsItem::StickDeadzone => {
+            ui.add(egui::Slider::new(&mut config.stick_deadzone, 0..=90).suffix("%"));
+        }
+        SettingsItem::Controls
+        | SettingsItem::Apply
+        | SettingsItem::Revert
+        | SettingsItem::Back => {
+            ui.label("");
+        }
+    }
+}
+
+/// Previews the edited config when it is changed.
+///
+/// The theme, the layout and the glyphs are previewed through `ConfigPreview`.
+#[allow(clippy::too_many_arguments)]
+fn preview_settings(
+    settings: Res<SettingsDraft>,
+    mut config_preview: ResMut<ConfigPreview>,
+    mut windows: ResMut<Windows>,
+    theme: Res<Theme>,
+    grid_query: Query<&Handle<ColorMaterial>, With<Grid>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut music_player: ResMut<MusicPlayer>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    if settings.is_changed() {
+        config_preview.0 = Some(settings.0.clone());
+        preview(
+            &settings.0,
+            windows.primary_mut(),
+            &theme,
+            &grid_query,
+            &mut materials,
+            &mut music_player,
+            &audio_sinks,
+        );
+    }
+}
+
+/// Restores the current config when leaving the settings screen without applying.
+#[allow(clippy::too_many_arguments)]
+fn discard_settings(
+    config: Res<Config>,
+    mut settings: ResMut<SettingsDraft>,
+    mut config_preview: ResMut<ConfigPreview>,
+    mut windows: ResMut<Windows>,
+    theme: Res<Theme>,
+    grid_query: Query<&Handle<ColorMaterial>, With<Grid>>,
+    mut materials: ResMut<Assets<ColorMaterial>>,
+    mut music_player: ResMut<MusicPlayer>,
+    audio_sinks: Res<Assets<AudioSink>>,
+) {
+    config_preview.0 = None;
+    if settings.0 != *config {
+        info!("Discarding unapplied settings");
+        settings.0 = config.clone();
+        preview(
+            &config,
+            windows.primary_mut(),
+            &theme,
+            &grid_query,
+            &mut materials,
+            &mut music_player,
+            &audio_sinks,
+        );
+    }
+}
+
+/// Reflects the config to the window, the grids and the music.
+fn preview(
+    config: &Config,
+    window: &mut Window,
+    theme: &Theme,
+    grid_query: &Query<&Handle<ColorMaterial>, With<Grid>>,
+    materials: &mut Assets<ColorMaterial>,
+    music_player: &mut MusicPlayer,
+    audio_sinks: &Assets<AudioSink>,
+) {
+    let present_mode = if config.vsync {
+        PresentMode::AutoVsync
+    } else {
+        PresentMode::AutoNoVsync
+    };
+    if window.present_mode() != present_mode {
+        window.set_present_mode(present_mode);
+    }
+    let window_mode = config.window_mode.to_window_mode();
+    if window.mode() != window_mode {
+        window.set_mode(window_mode);
+    }
+
+    set_grid_opacity(config.grid_opacity, theme, grid_query, materials);
+
+    music_player.set_volume(config.music_volume, audio_sinks);
+}
@@ -0,0 +1,81 @@
+use super::*;
+use crate::style::*;
+use bevy::app::AppExit;
+use bevy_egui::{egui, EguiContext};
+use kgrs_core::{layout::Layout, theme::Theme};
+
+pub(crate) struct TitlePlugin;
+
+impl Plugin for TitlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(SystemSet::on_enter(AppState::Title).with_system(reset_cursor))
+            .add_system_set(SystemSet::on_update(AppState::Title).with_system(title));
+    }
+}
+
+/// Items of the title menu
+#[derive(Clone, Copy)]
+enum TitleItem {
+    Play,
+    Profile,
+    Settings,
+    Quit,
+}
+
+impl TitleItem {
+    const ALL: [Self; 4] = [Self::Play, Self::Profile, Self::Settings, Self::Quit];
+
+    fn label(&self) -> String {
+        match self {
+            Self::Play => "Play",
+            Self::Profile => "Profile",
+            Self::Settings => "Settings",
+            Self::Quit => "Quit",
+        }
+        .to_string()
+    }
+}
+
+/// Title screen
+fn title(
+    mut egui_ctx: ResMut<EguiContext>,
+    layout: Res<Layout>,
+    mut input: ResMut<Input<KeyCode>>,
+    mut cursor: ResMut<MenuCursor>,
+    mut state: ResMut<State<AppState>>,
+    mut exit_writer: EventWriter<AppExit>,
+    theme: Res<Theme>,
+) {
+    let window_height = layout.window.y;
+    let mut is_confirmed = cursor.navigate(&mut input, TitleItem::ALL.len());
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none().fill(egui_color(theme.background)))
+        .show(egui_ctx.ctx_mut(), |ui| {
+            ui.vertical_centered(|ui_c| {
+                ui_c.add_space(window_height * 0.15);
+                ui_c.label(logo(layout.logo_font_size));
+                ui_c.label(format!("v{}", env!("CARGO_PKG_VERSION")));
+                ui_c.add_space(window_height * 0.1);
+
+                let labels = TitleItem::ALL.map(|item| item.label());
+                let font_size = layout.menu_font_size;
+                if let Some(i) = menu_items(ui_c, &labels, cursor.0, font_size) {
+                    cursor.0 = i;
+                    is_confirmed = true;
+                }
+            });
+        });
+
+    if is_confirmed {
+        match TitleItem::ALL[cursor.0] {
+            TitleItem::Play => go_to(&mut state, AppState::ModeSelect),
+            TitleItem::Profile => go_to(&mut state, AppState::ProfileSelect),
+            TitleItem::Settings => go_to(&mut state, AppState::Settings),
+            TitleItem::Quit => {
+                info!("Quitting");
+                exit_writer.send(AppExit);
+            }
+        }
+    }
+}
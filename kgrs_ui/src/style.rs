@@ -0,0 +1,54 @@
+use bevy::prelude::*;
+use bevy_egui::egui::{self, text::LayoutJob};
+use kgrs_const::color::LOGO_COLS;
+
+/// Converts `Color` to egui's color.
+pub(crate) fn egui_color(color: Color) -> egui::Color32 {
+    egui::Color32::from_rgba_unmultiplied(
+        (color.r() * 255.) as u8,
+        (color.g() * 255.) as u8,
+        (color.b() * 255.) as u8,
+        (color.a() * 255.) as u8,
+    )
+}
+
+/// Returns the colored logo "KaGRiS".
+pub(crate) fn logo(font_size: f32) -> LayoutJob {
+    let mut job = LayoutJob::default();
+    for (letter, color) in "KaGRiS".chars().zip(LOGO_COLS) {
+        job.append(
+            &letter.to_string(),
+            0.,
+            egui::TextFormat {
+                font_id: egui::FontId::proportional(font_size),
+                color: egui_color(color),
+                ..default()
+            },
+        );
+    }
+    job
+}
+
+/// Draws the items of a menu with highlighting the item at the cursor.
+///
+/// Returns the index of the clicked item.
+pub(crate) fn menu_items(
+    ui: &mut egui::Ui,
+    labels: &[String],
+    cursor: usize,
+    font_size: f32,
+) -> Option<usize> {
+    let mut clicked = None;
+    for (i, label) in labels.iter().enumerate() {
+        let text = egui::RichText::new(label).size(font_size);
+        if ui.selectable_label(i == cursor, text).clicked() {
+            clicked = Some(i);
+        }
+    }
+    clicked
+}
+
+/// Formats seconds as `m:ss.sss`.
+pub(crate) fn format_time(secs: f32) -> String {
+    format!("{}:{:06.3}", (secs / 60.) as u32, secs % 60.)
+}
@@ -1,17 +1,32 @@
-use bevy::{prelude::*, time::FixedTimestep};
+pub mod path {
+    use std::path::{Component, Path};
 
-/// Returns a SystemSet that runs the system 60 times per second.
-///
-/// # Examples
-///
-/// ```
-/// app.add_system_set(fixed_update(foo_system));
-/// ```
-pub fn fixed_update<T>(system: impl IntoSystemDescriptor<T>) -> SystemSet {
-    let step = 0.01666666666666666666666666666666667; // 1/60
-    SystemSet::new()
-        .with_run_criteria(FixedTimestep::step(step))
-        .with_system(system)
+    /// Whether the name is a plain file name which stays in the directory it is joined to.
+    ///
+    /// The names with separators, `..` or a root are rejected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use kgrs_util::function::path::is_file_name;
+    ///
+    /// assert!(is_file_name("midnight"));
+    /// assert!(is_file_name("move.wav"));
+    /// assert!(!is_file_name(""));
+    /// assert!(!is_file_name(".."));
+    /// assert!(!is_file_name("../config"));
+    /// assert!(!is_file_name("skins/a.png"));
+    /// assert!(!is_file_name("a\\b"));
+    /// assert!(!is_file_name("/etc"));
+    /// ```
+    pub fn is_file_name(name: &str) -> bool {
+        !name.contains(['/', '\\'])
+            && !name.contains("..")
+            && matches!(
+                Path::new(name).components().collect::<Vec<_>>()[..],
+                [Component::Normal(_)]
+            )
+    }
 }
 
 pub mod fmt {
@@ -1,3 +1,4 @@
 //! Utilities for KaGRiS
 
 pub mod function;
+pub mod state;
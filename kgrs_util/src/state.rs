@@ -0,0 +1,24 @@
+//! States of the application
+
+/// Screens of the application
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum AppState {
+    /// Asking what to do with the invalid config.json
+    ConfigPrompt,
+    /// Selecting the profile of the player
+    ProfileSelect,
+    /// Title screen
+    Title,
+    /// Selecting the game mode to play
+    ModeSelect,
+    /// Settings screen
+    Settings,
+    /// Rebinding the keys, opened from the settings screen
+    Controls,
+    /// Playing a game
+    InGame,
+    /// Pause menu pushed on top of `InGame`
+    Paused,
+    /// Results of the finished game
+    Results,
+}